@@ -1,4 +1,4 @@
-/* Generated with cbindgen:0.20.0 */
+/* Generated with cbindgen:0.26.0 */
 
 /* Warning, this file is autogenerated by cbindgen. Don't modify this manually. */
 
@@ -7,6 +7,156 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * The coordinate axis on which a failure occurred
+ */
+typedef enum CoordinateAxis {
+    /**
+     * The failure isn't specific to one axis
+     */
+    CoordinateAxis_None = 0,
+    CoordinateAxis_Latitude,
+    CoordinateAxis_Longitude,
+} CoordinateAxis;
+
+/**
+ * The order of the values in each coordinate pair
+ *
+ * `LonLat` matches the `(x, y)` order of `geo-types`, and is used by the functions which don't take an order.
+ * `LatLon` matches the order used by the Polyline algorithm and its first-party documentation.
+ *
+ * Functions taking an order accept it as an unsigned 32-bit `int`, and reject unknown values.
+ */
+typedef enum CoordinateOrder {
+    CoordinateOrder_LonLat = 0,
+    CoordinateOrder_LatLon,
+} CoordinateOrder;
+
+/**
+ * How [`polyline_interpolate`](fn.polyline_interpolate.html) measures the distance along a Polyline
+ *
+ * - `Fraction` is a fraction of the Polyline's length, from `0.0` to `1.0`
+ * - `Metres` is a distance in metres, from `0.0` to the Polyline's length
+ */
+typedef enum InterpolationMode {
+    InterpolationMode_Fraction = 0,
+    InterpolationMode_Metres,
+} InterpolationMode;
+
+/**
+ * The method used to measure distances between coordinates
+ *
+ * - `Haversine` treats the Earth as a sphere. It is the fastest, and is accurate to within about 0.5%
+ * - `Vincenty` uses Vincenty's formulae on the WGS84 ellipsoid. It can fail to converge for nearly antipodal points
+ * - `Geodesic` uses Karney's geodesic algorithm on the WGS84 ellipsoid. It is the most accurate, and always converges
+ */
+typedef enum LengthMethod {
+    LengthMethod_Haversine = 0,
+    LengthMethod_Vincenty,
+    LengthMethod_Geodesic,
+} LengthMethod;
+
+/**
+ * Status codes returned by the checked FFI entry points
+ *
+ * `Ok` is always `0`, so callers can test for success with a simple comparison.
+ */
+typedef enum PolylineStatus {
+    /**
+     * The call succeeded
+     */
+    PolylineStatus_Ok = 0,
+    /**
+     * The input string was not valid UTF-8
+     */
+    PolylineStatus_InvalidUtf8,
+    /**
+     * An unsupported precision value was supplied
+     */
+    PolylineStatus_InvalidPrecision,
+    /**
+     * The input contained a character that is not part of the Polyline alphabet
+     */
+    PolylineStatus_InvalidCharacter,
+    /**
+     * The input ended part-way through a value, or a latitude had no longitude
+     */
+    PolylineStatus_TruncatedInput,
+    /**
+     * A coordinate fell outside the range `-90.0..=90.0` (latitude) or `-180.0..=180.0` (longitude)
+     */
+    PolylineStatus_CoordinateOutOfRange,
+    /**
+     * A required pointer argument was `NULL`
+     */
+    PolylineStatus_NullPointer,
+    /**
+     * A coordinate could not be encoded
+     */
+    PolylineStatus_EncodingError,
+    /**
+     * An array length was too large to be addressed
+     */
+    PolylineStatus_InvalidLength,
+    /**
+     * The library panicked. The panic was contained, and the call had no effect
+     */
+    PolylineStatus_Panic,
+    /**
+     * A caller-provided buffer was too small to hold the output
+     */
+    PolylineStatus_BufferTooSmall,
+    /**
+     * An offsets array was decreasing, or pointed past the end of its data
+     */
+    PolylineStatus_InvalidOffset,
+    /**
+     * A thread pool could not be created. Only returned when built with the `parallel` feature
+     */
+    PolylineStatus_ThreadPoolError,
+    /**
+     * A numeric argument was out of range, such as a negative tolerance
+     */
+    PolylineStatus_InvalidArgument,
+    /**
+     * Vincenty's formulae failed to converge, which can happen for nearly antipodal points
+     */
+    PolylineStatus_NoConvergence,
+} PolylineStatus;
+
+/**
+ * The line simplification algorithm to use
+ *
+ * - `DouglasPeucker` removes points which are closer than the tolerance to the simplified line.
+ *   The tolerance is a distance, in degrees
+ * - `VisvalingamWhyatt` removes points which form a triangle with their neighbours whose area is smaller than the tolerance.
+ *   The tolerance is an area, in square degrees
+ * - `VisvalingamWhyattPreserve` is a variant of `VisvalingamWhyatt` which keeps points whose removal would
+ *   cause the simplified line to intersect itself. It is slower
+ */
+typedef enum SimplifyAlgorithm {
+    SimplifyAlgorithm_DouglasPeucker = 0,
+    SimplifyAlgorithm_VisvalingamWhyatt,
+    SimplifyAlgorithm_VisvalingamWhyattPreserve,
+} SimplifyAlgorithm;
+
+/**
+ * An opaque handle to a streaming Polyline decoder
+ *
+ * Created by [`decoder_new`](fn.decoder_new.html), and freed by [`decoder_free`](fn.decoder_free.html).
+ * A decoder holds the running coordinate totals between calls, so a Polyline may be split at any byte.
+ */
+typedef struct PolylineDecoder PolylineDecoder;
+
+/**
+ * An opaque handle to an incremental Polyline encoder
+ *
+ * Created by [`encoder_new`](fn.encoder_new.html), and freed by [`encoder_free`](fn.encoder_free.html).
+ * An encoder holds the Polyline encoded so far, and the last coordinate pushed, so each new coordinate is encoded
+ * as a single delta.
+ */
+typedef struct PolylineEncoder PolylineEncoder;
+
 /**
  * A C-compatible `struct` originating **inside** Rust
  * used for passing arrays across the FFI boundary
@@ -16,6 +166,21 @@ typedef struct InternalArray {
     size_t len;
 } InternalArray;
 
+/**
+ * The outcome of a checked FFI call
+ *
+ * `position` is the byte offset into the input string at which a decoding failure occurred,
+ * or the index of the coordinate at which an encoding or measuring failure occurred.
+ * It is `0` when `status` is `Ok`, or when the failure has no meaningful position.
+ *
+ * `axis` identifies the offending axis of a `CoordinateOutOfRange` failure.
+ */
+typedef struct PolylineResult {
+    enum PolylineStatus status;
+    size_t position;
+    enum CoordinateAxis axis;
+} PolylineResult;
+
 /**
  * A C-compatible `struct` originating **outside** Rust
  * used for passing arrays across the FFI boundary
@@ -25,6 +190,84 @@ typedef struct ExternalArray {
     size_t len;
 } ExternalArray;
 
+/**
+ * A C-compatible `struct` originating **inside** Rust, holding the results of decoding a batch of Polylines
+ *
+ * - `coords` holds the lon, lat coordinates of every successfully-decoded Polyline, one after another
+ * - `offsets` points to `count + 1` `size_t` values: the coordinates of item `i` are
+ *   `coords[offsets[i]..offsets[i + 1]]`, so a failed item has no coordinates
+ * - `statuses` points to `count` [PolylineResult](struct.PolylineResult.html) values, one for each item
+ * - `count` is the number of items in the batch
+ */
+typedef struct DecodedBatch {
+    struct InternalArray coords;
+    size_t *offsets;
+    struct PolylineResult *statuses;
+    size_t count;
+} DecodedBatch;
+
+/**
+ * A C-compatible `struct` originating **inside** Rust, holding the results of encoding a batch of Polylines
+ *
+ * - `data` points to `len` bytes, holding every successfully-encoded Polyline one after another, with no `NUL` terminators
+ * - `offsets` points to `count + 1` `size_t` values: the Polyline for item `i` is the bytes
+ *   `data[offsets[i]..offsets[i + 1]]`, so a failed item has no bytes
+ * - `statuses` points to `count` [PolylineResult](struct.PolylineResult.html) values, one for each item
+ * - `count` is the number of items in the batch
+ */
+typedef struct EncodedBatch {
+    char *data;
+    size_t len;
+    size_t *offsets;
+    struct PolylineResult *statuses;
+    size_t count;
+} EncodedBatch;
+
+/**
+ * The extent of a Polyline's coordinates, in degrees
+ *
+ * `west` and `east` are the minimum and maximum longitudes, and `south` and `north` the minimum and maximum latitudes.
+ * If the box crosses the antimeridian, `west` is greater than `east`.
+ */
+typedef struct BoundingBox {
+    double west;
+    double south;
+    double east;
+    double north;
+} BoundingBox;
+
+/**
+ * A C-compatible `struct` originating **inside** Rust
+ * used for passing arrays of numbers across the FFI boundary: `data` points to `len` values
+ */
+typedef struct OwnedSlice_f64 {
+    double *data;
+    size_t len;
+} OwnedSlice_f64;
+
+/**
+ * A C-compatible `struct` originating **inside** Rust, holding `double` values
+ *
+ * `data` points to `len` `double` values.
+ */
+typedef struct OwnedSlice_f64 DoubleArray;
+
+/**
+ * A C-compatible `struct` originating **inside** Rust
+ * used for passing arrays of numbers across the FFI boundary: `data` points to `len` values
+ */
+typedef struct OwnedSlice_size_t {
+    size_t *data;
+    size_t len;
+} OwnedSlice_size_t;
+
+/**
+ * A C-compatible `struct` originating **inside** Rust, holding indices into an array of coordinates
+ *
+ * `data` points to `len` `size_t` values, in ascending order.
+ */
+typedef struct OwnedSlice_size_t IndexArray;
+
 /**
  * Convert a Polyline into an array of coordinates
  *
@@ -32,9 +275,10 @@ typedef struct ExternalArray {
  *
  * - a pointer to `NUL`-terminated characters (`char*`)
  * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
- * OSRM and Valhalla Polylines)
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
  *
  * A decoding failure will return an [Array](struct.Array.html) whose `data` field is `[[NaN, NaN]]`, and whose `len` field is `1`.
+ * The reason for the failure can be retrieved using [`polyline_last_error_message`](fn.polyline_last_error_message.html).
  *
  * Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
  * with the returned [Array](struct.Array.html), in order to free the memory it allocates.
@@ -46,6 +290,59 @@ typedef struct ExternalArray {
 struct InternalArray decode_polyline_ffi(const char *pl,
                                          uint32_t precision);
 
+/**
+ * Convert a Polyline into an array of coordinates, reporting the reason for any failure
+ *
+ * Callers must pass three arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a pointer to an [InternalArray](struct.InternalArray.html), which will receive the decoded coordinates
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html). If its `status` is anything other than `Ok`,
+ * `out` will hold an empty array, and `position` will hold the byte offset of the failure where one is known.
+ * Unlike [`decode_polyline_ffi`](fn.decode_polyline_ffi.html), a final value with no terminating chunk is reported
+ * as `TruncatedInput`.
+ *
+ * Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
+ * with the array written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decode_polyline_ffi_checked(const char *pl,
+                                                  uint32_t precision,
+                                                  struct InternalArray *out);
+
+/**
+ * Convert a length-delimited Polyline into an array of coordinates, reporting the reason for any failure
+ *
+ * Callers must pass four arguments:
+ *
+ * - a pointer to the Polyline's bytes (`uint8_t*`), which need not be `NUL`-terminated
+ * - the number of bytes, as a `size_t`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a pointer to an [InternalArray](struct.InternalArray.html), which will receive the decoded coordinates
+ *
+ * The bytes must be valid UTF-8. This allows Polylines to be decoded in place from a larger buffer.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ *
+ * Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
+ * with the array written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decode_polyline_bytes(const uint8_t *data,
+                                            size_t len,
+                                            uint32_t precision,
+                                            struct InternalArray *out);
+
 /**
  * Convert an array of coordinates into a Polyline
  *
@@ -55,13 +352,16 @@ struct InternalArray decode_polyline_ffi(const char *pl,
  *     - `data`, a void pointer to an array of floating-point lat, lon coordinates: `[[1.0, 2.0]]`
  *     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
  * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
- * OSRM and Valhalla Polylines)
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
  *
  * A decoding failure will return one of the following:
  *
  * - a `char*` beginning with "Longitude error:" if invalid longitudes are passed
  * - a `char*` beginning with "Latitude error:" if invalid latitudes are passed
  *
+ * As the error is returned in-band, callers should check [`polyline_last_error_length`](fn.polyline_last_error_length.html),
+ * which is `0` if the call succeeded.
+ *
  * Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
  * with the returned `c_char` pointer, in order to free the memory it allocates.
  *
@@ -72,6 +372,96 @@ struct InternalArray decode_polyline_ffi(const char *pl,
 char *encode_coordinates_ffi(struct ExternalArray coords,
                              uint32_t precision);
 
+/**
+ * Convert an array of coordinates into a Polyline, reporting the reason for any failure
+ *
+ * Callers must pass three arguments:
+ *
+ * - a [Struct](struct.ExternalArray.html) with two fields:
+ *     - `data`, a void pointer to an array of floating-point lon, lat coordinates: `[[2.0, 1.0]]`
+ *     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a pointer to a `char*`, which will receive the encoded Polyline
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html). If its `status` is anything other than `Ok`,
+ * `out` will be set to `NULL`. If a coordinate is out of range, `position` will hold its index in
+ * the array, and `axis` will identify whether its latitude or longitude was invalid.
+ *
+ * Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+ * with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult encode_coordinates_ffi_checked(struct ExternalArray coords,
+                                                     uint32_t precision,
+                                                     char **out);
+
+/**
+ * Convert a Polyline into an array of coordinates in the requested order, reporting the reason for any failure
+ *
+ * Callers must pass four arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a [CoordinateOrder](enum.CoordinateOrder.html) value as an unsigned 32-bit `int`, specifying the order of each
+ *   coordinate pair in the output
+ * - a pointer to an [InternalArray](struct.InternalArray.html), which will receive the decoded coordinates
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * The status is `InvalidArgument` if `order` isn't a `CoordinateOrder` value.
+ *
+ * Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
+ * with the array written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decode_polyline_ordered(const char *pl,
+                                              uint32_t precision,
+                                              uint32_t order,
+                                              struct InternalArray *out);
+
+/**
+ * Convert an array of coordinates in the given order into a Polyline, reporting the reason for any failure
+ *
+ * Callers must pass four arguments:
+ *
+ * - a [Struct](struct.ExternalArray.html) with two fields:
+ *     - `data`, a void pointer to an array of floating-point coordinate pairs, in the order given by `order`
+ *     - `len`, the length of the array being passed. Its type must be `size_t`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a [CoordinateOrder](enum.CoordinateOrder.html) value as an unsigned 32-bit `int`, specifying the order of each
+ *   coordinate pair in `coords`
+ * - a pointer to a `char*`, which will receive the encoded Polyline
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`encode_coordinates_ffi_checked`](fn.encode_coordinates_ffi_checked.html).
+ * The status is `InvalidArgument` if `order` isn't a `CoordinateOrder` value.
+ *
+ * Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+ * with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult encode_coordinates_ordered(struct ExternalArray coords,
+                                                 uint32_t precision,
+                                                 uint32_t order,
+                                                 char **out);
+
+/**
+ * Return the largest precision value accepted by this library
+ *
+ * Every function taking a precision argument accepts values from `0` up to and including this value.
+ */
+uint32_t polyline_max_precision(void);
+
 /**
  * Free Array memory which Rust has allocated across the FFI boundary
  *
@@ -79,13 +469,827 @@ char *encode_coordinates_ffi(struct ExternalArray coords,
  *
  * This function is unsafe because it accesses a raw pointer which could contain arbitrary data
  */
-void drop_float_array(struct InternalArray);
+void drop_float_array(struct InternalArray arr);
 
 /**
  * Free `CString` memory which Rust has allocated across the FFI boundary
  *
+ * Passing `NULL` has no effect.
+ *
  * # Safety
  *
  * This function is unsafe because it accesses a raw pointer which could contain arbitrary data
  */
 void drop_cstring(char *p);
+
+/**
+ * Convert a batch of length-delimited Polylines into coordinates, in a single call
+ *
+ * Callers must pass five arguments:
+ *
+ * - a pointer to an array of `count` pointers to Polyline bytes (`const uint8_t**`), which need not be `NUL`-terminated
+ * - a pointer to an array of `count` lengths (`size_t*`), one for each Polyline
+ * - the number of Polylines, as a `size_t`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a pointer to a [DecodedBatch](struct.DecodedBatch.html), which will receive the results
+ *
+ * Each Polyline is decoded independently: a Polyline which fails to decode has no coordinates in the output,
+ * and its failure is recorded in the `statuses` array, leaving the rest of the batch unaffected.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html) describing failures which affect the whole batch,
+ * such as an invalid precision. In that case `out` will hold an empty batch.
+ *
+ * Implementations calling this function **must** call [`drop_decoded_batch`](fn.drop_decoded_batch.html)
+ * with the batch written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decode_polylines_batch(const uint8_t *const *pls,
+                                             const size_t *lens,
+                                             size_t count,
+                                             uint32_t precision,
+                                             struct DecodedBatch *out);
+
+/**
+ * Free [DecodedBatch](struct.DecodedBatch.html) memory which Rust has allocated across the FFI boundary
+ */
+void drop_decoded_batch(struct DecodedBatch batch);
+
+/**
+ * Convert a batch of coordinate sequences into Polylines, in a single call
+ *
+ * Callers must pass five arguments:
+ *
+ * - a [Struct](struct.ExternalArray.html) holding the lon, lat coordinates of every sequence, one after another
+ * - a pointer to an array of `count + 1` offsets (`size_t*`): the coordinates of sequence `i` are
+ *   `coords[offsets[i]..offsets[i + 1]]`. Offsets must be non-decreasing, and may not exceed the length of `coords`.
+ *   The pointer may be `NULL` if `count` is `0`
+ * - the number of sequences, as a `size_t`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a pointer to an [EncodedBatch](struct.EncodedBatch.html), which will receive the results
+ *
+ * Each sequence is encoded independently: a sequence which fails to encode has no bytes in the output,
+ * and its failure is recorded in the `statuses` array, leaving the rest of the batch unaffected.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html) describing failures which affect the whole batch,
+ * such as an invalid precision, or an `InvalidOffset` status whose `position` is the index of the first bad offset.
+ * In that case `out` will hold an empty batch.
+ *
+ * Implementations calling this function **must** call [`drop_encoded_batch`](fn.drop_encoded_batch.html)
+ * with the batch written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult encode_coordinates_batch(struct ExternalArray coords,
+                                               const size_t *offsets,
+                                               size_t count,
+                                               uint32_t precision,
+                                               struct EncodedBatch *out);
+
+/**
+ * Free [EncodedBatch](struct.EncodedBatch.html) memory which Rust has allocated across the FFI boundary
+ */
+void drop_encoded_batch(struct EncodedBatch batch);
+
+/**
+ * Convert a Polyline into coordinates, writing them into a caller-provided buffer
+ *
+ * Callers must pass five arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a pointer to a `double` buffer, which will receive lon, lat pairs: `[2.0, 1.0, 4.0, 3.0]`
+ * - the capacity of the buffer, in coordinate **pairs**. Its type must be `size_t`
+ * - a pointer to a `size_t`, which will receive the number of coordinate pairs written
+ *
+ * This function does not allocate. If the buffer is too small, the status will be `BufferTooSmall`,
+ * and the required capacity (in coordinate pairs) will be written to `out_len`.
+ * The buffer pointer may be `NULL` if its capacity is `0`, so callers can query the required capacity up front.
+ * On any other failure, `out_len` will be set to `0`. The contents of the buffer are unspecified after a failure.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decode_polyline_into(const char *pl,
+                                           uint32_t precision,
+                                           double *out_buf,
+                                           size_t out_capacity,
+                                           size_t *out_len);
+
+/**
+ * Convert coordinates into a Polyline, writing it into a caller-provided buffer
+ *
+ * Callers must pass five arguments:
+ *
+ * - a [Struct](struct.ExternalArray.html) with two fields:
+ *     - `data`, a void pointer to an array of floating-point lon, lat coordinates: `[[2.0, 1.0]]`
+ *     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a pointer to a `char` buffer, which will receive the `NUL`-terminated Polyline
+ * - the capacity of the buffer in bytes, including space for the `NUL` terminator. Its type must be `size_t`
+ * - a pointer to a `size_t`, which will receive the number of bytes written, excluding the `NUL` terminator
+ *
+ * This function does not allocate. If the buffer is too small, the status will be `BufferTooSmall`,
+ * and the required capacity (including the `NUL` terminator) will be written to `out_len`.
+ * [`encoded_length_upper_bound`](fn.encoded_length_upper_bound.html) can be used to size the buffer up front.
+ * On any other failure, `out_len` will be set to `0`. The contents of the buffer are unspecified after a failure.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult encode_coordinates_into(struct ExternalArray coords,
+                                              uint32_t precision,
+                                              char *out_buf,
+                                              size_t out_capacity,
+                                              size_t *out_len);
+
+/**
+ * Calculate the size of a buffer which can hold the encoding of any `len` coordinates
+ *
+ * Callers must pass two arguments:
+ *
+ * - the number of coordinates to be encoded, as a `size_t`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ *
+ * Returns a capacity in bytes, including space for the `NUL` terminator, which is suitable for
+ * passing to [`encode_coordinates_into`](fn.encode_coordinates_into.html).
+ * Returns `0` if the precision is invalid or the size would overflow a `size_t`.
+ */
+size_t encoded_length_upper_bound(size_t len,
+                                  uint32_t precision);
+
+/**
+ * Retrieve a description of the most recent failure on the calling thread
+ *
+ * Every encoding or decoding call replaces the stored message, so this describes the most recent call only.
+ * Returns a `NUL`-terminated `char*`, or `NULL` if the most recent call succeeded.
+ *
+ * The returned pointer is owned by the library, and remains valid until the next encoding or
+ * decoding call on the same thread. Callers **must not** pass it to [`drop_cstring`](fn.drop_cstring.html).
+ */
+const char *polyline_last_error_message(void);
+
+/**
+ * Retrieve the length in bytes of the most recent failure message on the calling thread
+ *
+ * The length excludes the trailing `NUL`. Returns `0` if the most recent call succeeded.
+ */
+size_t polyline_last_error_length(void);
+
+/**
+ * Count the points in a Polyline, without decoding it
+ *
+ * Callers must pass three arguments:
+ *
+ * - a pointer to the Polyline bytes (`const uint8_t*`), which need not be `NUL`-terminated
+ * - the number of bytes, as a `size_t`
+ * - a pointer to a `size_t`, which will receive the number of points
+ *
+ * The bytes must be valid UTF-8. This function does not allocate, and doesn't need a precision,
+ * so coordinates are not checked for range.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * On failure, `out_count` will be set to `0`.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_point_count(const uint8_t *pl,
+                                           size_t len,
+                                           size_t *out_count);
+
+/**
+ * Check that a Polyline is well-formed, without decoding it
+ *
+ * Callers must pass four arguments:
+ *
+ * - a pointer to the Polyline bytes (`const uint8_t*`), which need not be `NUL`-terminated
+ * - the number of bytes, as a `size_t`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a `bool`: if `true`, every coordinate is also checked to be in the range `-90.0..=90.0` (latitude)
+ *   and `-180.0..=180.0` (longitude)
+ *
+ * Every byte must be in the range `63..=126`, every value must be terminated, and every latitude
+ * must have a longitude. This function does not allocate.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), whose `position` is the byte offset of the first problem found.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_validate(const uint8_t *pl,
+                                        size_t len,
+                                        uint32_t precision,
+                                        bool check_bounds);
+
+/**
+ * Guess the precision of a Polyline
+ *
+ * Callers must pass three arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - a pointer to an unsigned 32-bit `int`, which will receive the most likely precision
+ * - a pointer to a `double`, which will receive a confidence value between `0.0` and `1.0`
+ *
+ * Precisions 5 and 6 are the candidates, and a precision which would put a coordinate out of range is ruled out.
+ * Only if both are ruled out are precisions 7 up to [`polyline_max_precision`](fn.polyline_max_precision.html)
+ * considered instead, and lower precisions are never guessed. Of the remaining candidates, the guess prefers precision 5,
+ * since a Polyline with precision 6 is only in range at precision 5 if it lies close to 0°, 0°. It also prefers
+ * precisions which don't put every coordinate very close to 0°, 0°, and those which put the typical distance between
+ * consecutive points closer to 50 metres. The confidence is the best candidate's share of the total score.
+ *
+ * This is a heuristic. Ruling a precision out is reliable, so a Polyline with precision 6 far from 0°, 0° is guessed
+ * with a confidence of `1.0`. But one with precision 5 can only be told apart from one with precision 6 by its position
+ * and the distances between its points, so unless it lies close to 0°, 0° it's guessed with a confidence below `0.9`.
+ * The distances between points can change the odds by a factor of at most 4, so precision 5 Polylines whose points
+ * are more than around 300 metres apart, or precision 6 Polylines near 0°, 0° whose points are less than around
+ * 30 metres apart, may be guessed wrongly, but with low confidence. A Polyline with no points is guessed to have a
+ * precision of 5, with low confidence.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * The status is `CoordinateOutOfRange` if a coordinate is out of range at every precision.
+ * On failure, `out_precision` and `out_confidence` will be set to `0`.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_guess_precision(const char *pl,
+                                               uint32_t *out_precision,
+                                               double *out_confidence);
+
+/**
+ * Find the bounding box of a Polyline, without decoding it
+ *
+ * Callers must pass five arguments:
+ *
+ * - a pointer to the Polyline bytes (`const uint8_t*`), which need not be `NUL`-terminated
+ * - the number of bytes, as a `size_t`
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a `bool`: if `true`, and a box crossing the antimeridian is narrower, the box crosses it, and `west` will be
+ *   greater than `east`. If `false`, `west` and `east` are the smallest and largest longitudes
+ * - a pointer to a [BoundingBox](struct.BoundingBox.html), which will receive the box
+ *
+ * The crossing box runs between the smallest and largest longitudes when they are measured eastwards from 0°,
+ * so it never contains 0°. This finds the narrowest box unless the points leave a wider gap between their longitudes
+ * somewhere other than around 0° or ±180°: longitudes of `30`, `150`, `-150` and `-30` give a box from `-150` to `150`,
+ * although one from `-30` to `-150` is narrower.
+ *
+ * The bytes must be valid UTF-8. Every coordinate is checked to be in range, and this function does not allocate.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * The status is `InvalidArgument` if the Polyline has no points.
+ * On failure, every field of `out_bbox` will be set to `0`.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_bbox(const uint8_t *pl,
+                                    size_t len,
+                                    uint32_t precision,
+                                    bool antimeridian,
+                                    struct BoundingBox *out_bbox);
+
+/**
+ * Measure the length of a Polyline, in metres
+ *
+ * Callers must pass four arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a [LengthMethod](enum.LengthMethod.html) value as an unsigned 32-bit `int`
+ * - a pointer to a `double`, which will receive the length
+ *
+ * The Polyline is decoded one coordinate at a time, without allocating.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * The status is `NoConvergence` if Vincenty's formulae failed to converge, with the index of the first point of the segment.
+ * The status is `InvalidArgument` if `method` isn't a `LengthMethod` value.
+ * On failure, `out_length` will be set to `0`.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_length_m(const char *pl,
+                                        uint32_t precision,
+                                        uint32_t method,
+                                        double *out_length);
+
+/**
+ * Measure the length of an array of coordinates, in metres
+ *
+ * Callers must pass four arguments:
+ *
+ * - a [Struct](struct.ExternalArray.html) with two fields:
+ *     - `data`, a void pointer to an array of floating-point coordinate pairs, in the order given by `order`
+ *     - `len`, the length of the array being passed. Its type must be `size_t`
+ * - a [CoordinateOrder](enum.CoordinateOrder.html) value as an unsigned 32-bit `int`, specifying the order of each
+ *   coordinate pair in `coords`
+ * - a [LengthMethod](enum.LengthMethod.html) value as an unsigned 32-bit `int`
+ * - a pointer to a `double`, which will receive the length
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`encode_coordinates_ffi_checked`](fn.encode_coordinates_ffi_checked.html).
+ * The status is `NoConvergence` if Vincenty's formulae failed to converge, with the index of the first point of the segment.
+ * The status is `InvalidArgument` if `order` isn't a `CoordinateOrder` value, or `method` isn't a `LengthMethod` value.
+ * On failure, `out_length` will be set to `0`.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult coordinates_length_m(struct ExternalArray coords,
+                                           uint32_t order,
+                                           uint32_t method,
+                                           double *out_length);
+
+/**
+ * Find the coordinate at a distance along a Polyline
+ *
+ * Callers must pass six arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a `double` distance along the Polyline, whose meaning depends on `mode`
+ * - an [InterpolationMode](enum.InterpolationMode.html) value as an unsigned 32-bit `int`
+ * - a pointer to two `double` values, which will receive the lon, lat coordinate
+ * - a pointer to a `size_t`, which will receive the index of the segment containing the coordinate.
+ *   Segment `i` runs from point `i` to point `i + 1`
+ *
+ * Distances are measured along great circles, using the haversine formula, and the coordinate is interpolated
+ * along the great circle between the segment's points.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * The status is `InvalidArgument` if the Polyline has fewer than two points, the distance is outside it, or `mode`
+ * isn't an `InterpolationMode` value.
+ * On failure, `out_coord` and `out_segment` will be set to `0`.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_interpolate(const char *pl,
+                                           uint32_t precision,
+                                           double distance,
+                                           uint32_t mode,
+                                           double *out_coord,
+                                           size_t *out_segment);
+
+/**
+ * Find the distance along a Polyline to each of its points, in metres
+ *
+ * Callers must pass three arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a pointer to a [DoubleArray](type.DoubleArray.html), which will receive the distances
+ *
+ * The array has one distance per decoded point, the first of which is `0.0`.
+ * Distances are measured along great circles, using the haversine formula.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * On failure, `out` will receive an empty array.
+ *
+ * Implementations calling this function **must** call [`drop_double_array`](fn.drop_double_array.html)
+ * with the array written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_cumulative_distances(const char *pl,
+                                                    uint32_t precision,
+                                                    DoubleArray *out);
+
+/**
+ * Free [DoubleArray](type.DoubleArray.html) memory which Rust has allocated across the FFI boundary
+ */
+void drop_double_array(DoubleArray arr);
+
+#if defined(POLYLINE_PARALLEL)
+/**
+ * Set the maximum number of threads used by the parallel batch functions
+ *
+ * Pass `0` to use the default, which is usually one thread per CPU core.
+ * Calls which are already running will finish on the previous threads.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), whose `status` is `ThreadPoolError` if the threads couldn't be created.
+ *
+ * Only available when built with the `parallel` feature.
+ */
+struct PolylineResult polyline_set_num_threads(size_t num_threads);
+#endif
+
+#if defined(POLYLINE_PARALLEL)
+/**
+ * Convert a batch of length-delimited Polylines into coordinates, spreading the work across multiple threads
+ *
+ * Takes the same arguments, and produces the same output, as [`decode_polylines_batch`](fn.decode_polylines_batch.html).
+ * The results are in input order, and are identical to those of the sequential function.
+ * If the thread pool can't be created, the returned status is `ThreadPoolError`, and the batch is empty.
+ *
+ * Implementations calling this function **must** call [`drop_decoded_batch`](fn.drop_decoded_batch.html)
+ * with the batch written to `out`, in order to free the memory it allocates.
+ *
+ * Only available when built with the `parallel` feature.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decode_polylines_batch_parallel(const uint8_t *const *pls,
+                                                      const size_t *lens,
+                                                      size_t count,
+                                                      uint32_t precision,
+                                                      struct DecodedBatch *out);
+#endif
+
+#if defined(POLYLINE_PARALLEL)
+/**
+ * Convert a batch of coordinate sequences into Polylines, spreading the work across multiple threads
+ *
+ * Takes the same arguments, and produces the same output, as [`encode_coordinates_batch`](fn.encode_coordinates_batch.html).
+ * The results are in input order, and are identical to those of the sequential function.
+ * If the thread pool can't be created, the returned status is `ThreadPoolError`, and the batch is empty.
+ *
+ * Implementations calling this function **must** call [`drop_encoded_batch`](fn.drop_encoded_batch.html)
+ * with the batch written to `out`, in order to free the memory it allocates.
+ *
+ * Only available when built with the `parallel` feature.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult encode_coordinates_batch_parallel(struct ExternalArray coords,
+                                                        const size_t *offsets,
+                                                        size_t count,
+                                                        uint32_t precision,
+                                                        struct EncodedBatch *out);
+#endif
+
+/**
+ * Simplify a Polyline
+ *
+ * Callers must pass six arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a `double` tolerance, whose meaning depends on the algorithm. It must be finite, and not negative
+ * - a [SimplifyAlgorithm](enum.SimplifyAlgorithm.html) value as an unsigned 32-bit `int`
+ * - a pointer to a `char*`, which will receive the simplified Polyline, at the same precision
+ * - a pointer to an [IndexArray](type.IndexArray.html), which will receive the indices of the retained points,
+ *   or `NULL` if they aren't needed
+ *
+ * The simplified Polyline only contains points from the input, so its coordinates are unchanged.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * The status is `InvalidArgument` if the tolerance is negative or not finite, or if `algorithm` isn't a
+ * `SimplifyAlgorithm` value.
+ *
+ * Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+ * with a non-`NULL` pointer written to `out`, and [`drop_index_array`](fn.drop_index_array.html)
+ * with any array written to `out_indices`, in order to free the memory they allocate.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_simplify(const char *pl,
+                                        uint32_t precision,
+                                        double tolerance,
+                                        uint32_t algorithm,
+                                        char **out,
+                                        IndexArray *out_indices);
+
+/**
+ * Simplify an array of coordinates
+ *
+ * Callers must pass five arguments:
+ *
+ * - a [Struct](struct.ExternalArray.html) with two fields:
+ *     - `data`, a void pointer to an array of floating-point lon, lat coordinates: `[[2.0, 1.0]]`
+ *     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
+ * - a `double` tolerance, whose meaning depends on the algorithm. It must be finite, and not negative
+ * - a [SimplifyAlgorithm](enum.SimplifyAlgorithm.html) value as an unsigned 32-bit `int`
+ * - a pointer to an [InternalArray](struct.InternalArray.html), which will receive the simplified coordinates
+ * - a pointer to an [IndexArray](type.IndexArray.html), which will receive the indices of the retained coordinates,
+ *   or `NULL` if they aren't needed
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`encode_coordinates_ffi_checked`](fn.encode_coordinates_ffi_checked.html).
+ * The status is `InvalidArgument` if the tolerance is negative or not finite, or if `algorithm` isn't a
+ * `SimplifyAlgorithm` value.
+ *
+ * Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
+ * with the array written to `out`, and [`drop_index_array`](fn.drop_index_array.html)
+ * with any array written to `out_indices`, in order to free the memory they allocate.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult simplify_coordinates(struct ExternalArray coords,
+                                           double tolerance,
+                                           uint32_t algorithm,
+                                           struct InternalArray *out,
+                                           IndexArray *out_indices);
+
+/**
+ * Free [IndexArray](type.IndexArray.html) memory which Rust has allocated across the FFI boundary
+ */
+void drop_index_array(IndexArray arr);
+
+/**
+ * Create a streaming Polyline decoder
+ *
+ * Callers must pass an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ * OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html)).
+ *
+ * Returns a pointer to a new decoder, or `NULL` if the precision is invalid.
+ * Input is passed to the decoder using [`decoder_feed`](fn.decoder_feed.html), and the decoded coordinates are retrieved
+ * using [`decoder_next_points`](fn.decoder_next_points.html).
+ * Once all the input has been fed, [`decoder_finish`](fn.decoder_finish.html) checks that it was complete.
+ *
+ * Implementations calling this function **must** call [`decoder_free`](fn.decoder_free.html)
+ * with the returned pointer, in order to free the memory it allocates.
+ */
+struct PolylineDecoder *decoder_new(uint32_t precision);
+
+/**
+ * Pass the next piece of a Polyline to a streaming decoder
+ *
+ * Callers must pass three arguments:
+ *
+ * - a pointer to a decoder created by [`decoder_new`](fn.decoder_new.html)
+ * - a pointer to the next bytes of the Polyline (`const uint8_t*`), which need not be `NUL`-terminated.
+ *   This may be `NULL` if the length is `0`
+ * - the number of bytes, as a `size_t`
+ *
+ * The input may be split at any byte. Every coordinate it completes is decoded immediately, and held by the decoder
+ * until it is retrieved using [`decoder_next_points`](fn.decoder_next_points.html), so callers should
+ * retrieve coordinates between calls in order to keep memory use low.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), whose `position` is an offset into the whole input fed so far.
+ * After a failure, the decoder will report the same failure from every subsequent call to this function
+ * and to [`decoder_finish`](fn.decoder_finish.html). Coordinates decoded before the failure can still be retrieved.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decoder_feed(struct PolylineDecoder *decoder,
+                                   const uint8_t *data,
+                                   size_t len);
+
+/**
+ * Retrieve decoded coordinates from a streaming decoder, writing them into a caller-provided buffer
+ *
+ * Callers must pass four arguments:
+ *
+ * - a pointer to a decoder created by [`decoder_new`](fn.decoder_new.html)
+ * - a pointer to a `double` buffer, which will receive lon, lat pairs: `[2.0, 1.0, 4.0, 3.0]`
+ * - the capacity of the buffer, in coordinate **pairs**. Its type must be `size_t`
+ * - a pointer to a `size_t`, which will receive the number of coordinate pairs written
+ *
+ * Coordinates are written in input order. If fewer pairs than the capacity are written, every coordinate
+ * decoded so far has been retrieved. On failure, `out_len` will be set to `0`.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decoder_next_points(struct PolylineDecoder *decoder,
+                                          double *out_buf,
+                                          size_t out_capacity,
+                                          size_t *out_len);
+
+/**
+ * Check that the input passed to a streaming decoder ended on a complete coordinate
+ *
+ * Callers must pass a pointer to a decoder created by [`decoder_new`](fn.decoder_new.html).
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), whose status is `TruncatedInput` if the input
+ * ended part-way through a value, or with a latitude which had no longitude, or the status of an earlier failure.
+ * This doesn't free the decoder, and coordinates which haven't been retrieved can still be retrieved afterwards.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult decoder_finish(struct PolylineDecoder *decoder);
+
+/**
+ * Free a streaming decoder, along with any coordinates which haven't been retrieved
+ *
+ * Callers must pass the same pointer they receive from [`decoder_new`](fn.decoder_new.html). Passing `NULL` has no effect.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+void decoder_free(struct PolylineDecoder *decoder);
+
+/**
+ * Create an incremental Polyline encoder
+ *
+ * Callers must pass an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ * OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html)).
+ *
+ * Returns a pointer to a new encoder, or `NULL` if the precision is invalid.
+ * Coordinates are appended using [`encoder_push`](fn.encoder_push.html) or [`encoder_push_many`](fn.encoder_push_many.html),
+ * and the Polyline encoded so far can be retrieved at any time using [`encoder_current`](fn.encoder_current.html).
+ *
+ * Implementations calling this function **must** call [`encoder_free`](fn.encoder_free.html)
+ * with the returned pointer, in order to free the memory it allocates.
+ */
+struct PolylineEncoder *encoder_new(uint32_t precision);
+
+/**
+ * Append a single coordinate to an incremental encoder
+ *
+ * Callers must pass three arguments:
+ *
+ * - a pointer to an encoder created by [`encoder_new`](fn.encoder_new.html)
+ * - the latitude, as a `double`
+ * - the longitude, as a `double`
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html). A coordinate which is out of range is not appended,
+ * and leaves the encoder unchanged.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult encoder_push(struct PolylineEncoder *encoder,
+                                   double lat,
+                                   double lon);
+
+/**
+ * Append an array of coordinates to an incremental encoder
+ *
+ * Callers must pass three arguments:
+ *
+ * - a pointer to an encoder created by [`encoder_new`](fn.encoder_new.html)
+ * - a [Struct](struct.ExternalArray.html) with two fields:
+ *     - `data`, a void pointer to an array of floating-point coordinate pairs, in the order given by `order`
+ *     - `len`, the length of the array being passed. Its type must be `size_t`
+ * - a [CoordinateOrder](enum.CoordinateOrder.html) value as an unsigned 32-bit `int`, specifying the order of each
+ *   coordinate pair in `coords`
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`encode_coordinates_ffi_checked`](fn.encode_coordinates_ffi_checked.html).
+ * The status is `InvalidArgument` if `order` isn't a `CoordinateOrder` value.
+ * If any coordinate is out of range, none of them are appended, and the encoder is unchanged.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult encoder_push_many(struct PolylineEncoder *encoder,
+                                        struct ExternalArray coords,
+                                        uint32_t order);
+
+/**
+ * Retrieve the Polyline encoded so far by an incremental encoder
+ *
+ * Callers must pass two arguments:
+ *
+ * - a pointer to an encoder created by [`encoder_new`](fn.encoder_new.html)
+ * - a pointer to a `char*`, which will receive a copy of the encoded Polyline, or `NULL` on failure
+ *
+ * The encoder is unchanged, and further coordinates can be appended afterwards.
+ *
+ * Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+ * with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult encoder_current(struct PolylineEncoder *encoder,
+                                      char **out);
+
+/**
+ * Free an incremental encoder
+ *
+ * Callers must pass the same pointer they receive from [`encoder_new`](fn.encoder_new.html). Passing `NULL` has no effect.
+ * Polylines retrieved using [`encoder_current`](fn.encoder_current.html) are unaffected, and must still be freed.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+void encoder_free(struct PolylineEncoder *encoder);
+
+/**
+ * Append coordinates to an encoded Polyline, without decoding it
+ *
+ * Callers must pass four arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`), holding the existing Polyline
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - a [Struct](struct.ExternalArray.html) with two fields:
+ *     - `data`, a void pointer to an array of floating-point lon, lat coordinates: `[[2.0, 1.0]]`
+ *     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
+ * - a pointer to a `char*`, which will receive the extended Polyline
+ *
+ * The last point of the existing Polyline is found by summing its deltas, so only the new coordinates are encoded,
+ * and the existing Polyline is copied unchanged.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html). The `position` of a failure in the existing Polyline is a byte offset,
+ * as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html), and the `position` of an out-of-range
+ * new coordinate is its index in `coords`.
+ *
+ * Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+ * with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_append(const char *pl,
+                                      uint32_t precision,
+                                      struct ExternalArray coords,
+                                      char **out);
+
+/**
+ * Join two encoded Polylines, without decoding them
+ *
+ * Callers must pass five arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`), holding the first Polyline
+ * - a pointer to `NUL`-terminated characters (`char*`), holding the second Polyline
+ * - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html)),
+ *   which must be the same for both Polylines
+ * - a `bool`: if `true`, and the second Polyline begins at the last point of the first,
+ *   that point is only included once in the output
+ * - a pointer to a `char*`, which will receive the joined Polyline
+ *
+ * Only the first delta of the second Polyline is rewritten, using the integer values of the encoding,
+ * so the joined Polyline decodes to exactly the coordinates of both inputs.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html). The `position` of a failure is a byte offset,
+ * counted as if the second Polyline followed the first.
+ *
+ * Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+ * with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_concat(const char *a,
+                                      const char *b,
+                                      uint32_t precision,
+                                      bool drop_joint,
+                                      char **out);
+
+/**
+ * Convert a Polyline from one precision to another, without decoding it into floating-point coordinates
+ *
+ * Callers must pass four arguments:
+ *
+ * - a pointer to `NUL`-terminated characters (`char*`)
+ * - an unsigned 32-bit `int` for the precision of the input (5 for Google Polylines, 6 for
+ *   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+ * - an unsigned 32-bit `int` for the precision of the output
+ * - a pointer to a `char*`, which will receive the converted Polyline
+ *
+ * The integer value of each coordinate is rescaled directly. When reducing precision, values are rounded
+ * half away from zero, which is how coordinates are rounded when encoding.
+ *
+ * Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+ * The status is `EncodingError` if increasing the precision of a value would overflow, with the `position` of its point.
+ *
+ * Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+ * with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it accesses raw pointers which could contain arbitrary data
+ */
+struct PolylineResult polyline_transcode(const char *pl,
+                                         uint32_t from_precision,
+                                         uint32_t to_precision,
+                                         char **out);
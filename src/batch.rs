@@ -0,0 +1,501 @@
+//! Entry points which encode or decode many Polylines in a single call
+
+use crate::error::{catch_panic, report, Error, PolylineResult};
+use crate::CoordinateOrder;
+use crate::{codec, get_precision, input_slice, str_from_raw_parts, try_vec_from_string};
+use crate::{ExternalArray, InternalArray};
+use libc::c_char;
+use std::ptr;
+
+// Leak a Vec across the FFI boundary as a pointer to its first element
+fn leak_slice<T>(v: Vec<T>) -> *mut T {
+    Box::into_raw(v.into_boxed_slice()).cast::<T>()
+}
+
+// Reclaim and free a slice leaked by leak_slice, which must have had length len
+unsafe fn free_slice<T>(p: *mut T, len: usize) {
+    if !p.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(p, len)));
+    }
+}
+
+/// A C-compatible `struct` originating **inside** Rust, holding the results of decoding a batch of Polylines
+///
+/// - `coords` holds the lon, lat coordinates of every successfully-decoded Polyline, one after another
+/// - `offsets` points to `count + 1` `size_t` values: the coordinates of item `i` are
+///   `coords[offsets[i]..offsets[i + 1]]`, so a failed item has no coordinates
+/// - `statuses` points to `count` [PolylineResult](struct.PolylineResult.html) values, one for each item
+/// - `count` is the number of items in the batch
+#[repr(C)]
+pub struct DecodedBatch {
+    pub coords: InternalArray,
+    pub offsets: *mut libc::size_t,
+    pub statuses: *mut PolylineResult,
+    pub count: libc::size_t,
+}
+
+impl DecodedBatch {
+    // A batch which owns no data, and is safe to drop
+    fn empty() -> Self {
+        DecodedBatch {
+            coords: InternalArray::empty(),
+            offsets: ptr::null_mut(),
+            statuses: ptr::null_mut(),
+            count: 0,
+        }
+    }
+}
+
+impl Drop for DecodedBatch {
+    fn drop(&mut self) {
+        // we originated this data, so pointer-to-slice -> box. coords frees itself
+        unsafe {
+            free_slice(self.offsets, self.count + 1);
+            free_slice(self.statuses, self.count);
+        }
+    }
+}
+
+// Flatten per-item decoding results into a batch
+fn assemble_decoded(results: Vec<Result<Vec<[f64; 2]>, Error>>) -> DecodedBatch {
+    let total = results.iter().flatten().map(Vec::len).sum();
+    let mut coords = Vec::with_capacity(total);
+    let mut offsets = Vec::with_capacity(results.len() + 1);
+    let mut statuses = Vec::with_capacity(results.len());
+    offsets.push(0);
+    for result in &results {
+        match result {
+            Ok(item) => {
+                coords.extend_from_slice(item);
+                statuses.push(PolylineResult::ok());
+            }
+            Err(e) => statuses.push(e.into()),
+        }
+        offsets.push(coords.len());
+    }
+    DecodedBatch {
+        coords: coords.into(),
+        offsets: leak_slice(offsets),
+        statuses: leak_slice(statuses),
+        count: results.len(),
+    }
+}
+
+// Borrow each item of a batch as a str. Items which can't be borrowed keep their error
+unsafe fn batch_items<'a>(
+    pls: *const *const u8,
+    lens: *const libc::size_t,
+    count: usize,
+) -> Result<Vec<Result<&'a str, Error>>, Error> {
+    let pls = input_slice(pls, count)?;
+    let lens = input_slice(lens, count)?;
+    Ok(pls
+        .iter()
+        .zip(lens)
+        .map(|(&pl, &len)| str_from_raw_parts(pl, len))
+        .collect())
+}
+
+// Decode a single item of a batch, keeping any error from borrowing it
+pub(crate) fn decode_item(
+    item: Result<&str, Error>,
+    precision: u32,
+) -> Result<Vec<[f64; 2]>, Error> {
+    item.and_then(|s| try_vec_from_string(s, precision, CoordinateOrder::LonLat))
+}
+
+// The result of each item of a batch, in input order, or a failure which affects the whole batch
+pub(crate) type BatchResults<T> = Result<Vec<Result<T, Error>>, Error>;
+
+// Decode the items of a batch one after another
+fn decode_items(items: Vec<Result<&str, Error>>, precision: u32) -> BatchResults<Vec<[f64; 2]>> {
+    Ok(items
+        .into_iter()
+        .map(|item| decode_item(item, precision))
+        .collect())
+}
+
+// Decode a batch of Polylines, keeping failures in place so one bad item doesn't fail the whole batch.
+// decode_items decodes the borrowed items, and must return its results in input order, or fail the whole batch
+pub(crate) unsafe fn decode_batch<F>(
+    pls: *const *const u8,
+    lens: *const libc::size_t,
+    count: libc::size_t,
+    precision: u32,
+    out: *mut DecodedBatch,
+    decode_items: F,
+) -> PolylineResult
+where
+    F: FnOnce(Vec<Result<&str, Error>>, u32) -> BatchResults<Vec<[f64; 2]>>,
+{
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let items = batch_items(pls, lens, count)?;
+        get_precision(precision).ok_or(Error::Precision(precision))?;
+        Ok(assemble_decoded(decode_items(items, precision)?))
+    });
+    match result {
+        Ok(batch) => {
+            out.write(batch);
+            report(Ok(()))
+        }
+        Err(e) => {
+            out.write(DecodedBatch::empty());
+            report(Err(e))
+        }
+    }
+}
+
+/// Convert a batch of length-delimited Polylines into coordinates, in a single call
+///
+/// Callers must pass five arguments:
+///
+/// - a pointer to an array of `count` pointers to Polyline bytes (`const uint8_t**`), which need not be `NUL`-terminated
+/// - a pointer to an array of `count` lengths (`size_t*`), one for each Polyline
+/// - the number of Polylines, as a `size_t`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a pointer to a [DecodedBatch](struct.DecodedBatch.html), which will receive the results
+///
+/// Each Polyline is decoded independently: a Polyline which fails to decode has no coordinates in the output,
+/// and its failure is recorded in the `statuses` array, leaving the rest of the batch unaffected.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html) describing failures which affect the whole batch,
+/// such as an invalid precision. In that case `out` will hold an empty batch.
+///
+/// Implementations calling this function **must** call [`drop_decoded_batch`](fn.drop_decoded_batch.html)
+/// with the batch written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decode_polylines_batch(
+    pls: *const *const u8,
+    lens: *const libc::size_t,
+    count: libc::size_t,
+    precision: u32,
+    out: *mut DecodedBatch,
+) -> PolylineResult {
+    decode_batch(pls, lens, count, precision, out, decode_items)
+}
+
+/// Free [DecodedBatch](struct.DecodedBatch.html) memory which Rust has allocated across the FFI boundary
+#[no_mangle]
+pub extern "C" fn drop_decoded_batch(batch: DecodedBatch) {
+    let _ = catch_panic(|| {
+        drop(batch);
+        Ok(())
+    });
+}
+
+/// A C-compatible `struct` originating **inside** Rust, holding the results of encoding a batch of Polylines
+///
+/// - `data` points to `len` bytes, holding every successfully-encoded Polyline one after another, with no `NUL` terminators
+/// - `offsets` points to `count + 1` `size_t` values: the Polyline for item `i` is the bytes
+///   `data[offsets[i]..offsets[i + 1]]`, so a failed item has no bytes
+/// - `statuses` points to `count` [PolylineResult](struct.PolylineResult.html) values, one for each item
+/// - `count` is the number of items in the batch
+#[repr(C)]
+pub struct EncodedBatch {
+    pub data: *mut c_char,
+    pub len: libc::size_t,
+    pub offsets: *mut libc::size_t,
+    pub statuses: *mut PolylineResult,
+    pub count: libc::size_t,
+}
+
+impl EncodedBatch {
+    // A batch which owns no data, and is safe to drop
+    fn empty() -> Self {
+        EncodedBatch {
+            data: ptr::null_mut(),
+            len: 0,
+            offsets: ptr::null_mut(),
+            statuses: ptr::null_mut(),
+            count: 0,
+        }
+    }
+}
+
+impl Drop for EncodedBatch {
+    fn drop(&mut self) {
+        // we originated this data, so pointer-to-slice -> box
+        unsafe {
+            free_slice(self.data, self.len);
+            free_slice(self.offsets, self.count + 1);
+            free_slice(self.statuses, self.count);
+        }
+    }
+}
+
+// Flatten per-item encoding results into a batch
+fn assemble_encoded(results: Vec<Result<Vec<u8>, Error>>) -> EncodedBatch {
+    let total = results.iter().flatten().map(Vec::len).sum();
+    let mut data: Vec<c_char> = Vec::with_capacity(total);
+    let mut offsets = Vec::with_capacity(results.len() + 1);
+    let mut statuses = Vec::with_capacity(results.len());
+    offsets.push(0);
+    for result in &results {
+        match result {
+            Ok(item) => {
+                data.extend(item.iter().map(|&b| b as c_char));
+                statuses.push(PolylineResult::ok());
+            }
+            Err(e) => statuses.push(e.into()),
+        }
+        offsets.push(data.len());
+    }
+    EncodedBatch {
+        len: data.len(),
+        data: leak_slice(data),
+        offsets: leak_slice(offsets),
+        statuses: leak_slice(statuses),
+        count: results.len(),
+    }
+}
+
+// Split flat coordinates into the items described by offsets, which must be non-decreasing and in bounds
+fn split_coords<'a>(
+    coords: &'a [[f64; 2]],
+    offsets: &[usize],
+) -> Result<Vec<&'a [[f64; 2]]>, Error> {
+    let mut items = Vec::with_capacity(offsets.len().saturating_sub(1));
+    for (idx, pair) in offsets.windows(2).enumerate() {
+        let item = coords
+            .get(pair[0]..pair[1])
+            .ok_or(Error::Offset { idx: idx + 1 })?;
+        items.push(item);
+    }
+    Ok(items)
+}
+
+// Encode a single item of a batch
+pub(crate) fn encode_item(coords: &[[f64; 2]], precision: u32) -> Result<Vec<u8>, Error> {
+    let mut encoded = vec![];
+    codec::encode_coords(coords, precision, &mut |b| encoded.push(b))?;
+    Ok(encoded)
+}
+
+// Encode the items of a batch one after another
+fn encode_items(items: Vec<&[[f64; 2]]>, precision: u32) -> BatchResults<Vec<u8>> {
+    Ok(items
+        .into_iter()
+        .map(|item| encode_item(item, precision))
+        .collect())
+}
+
+// Encode a batch of coordinate sequences, keeping failures in place so one bad item doesn't fail the whole batch.
+// encode_items encodes the split items, and must return its results in input order, or fail the whole batch
+pub(crate) unsafe fn encode_batch<F>(
+    coords: ExternalArray,
+    offsets: *const libc::size_t,
+    count: libc::size_t,
+    precision: u32,
+    out: *mut EncodedBatch,
+    encode_items: F,
+) -> PolylineResult
+where
+    F: FnOnce(Vec<&[[f64; 2]]>, u32) -> BatchResults<Vec<u8>>,
+{
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        // an empty batch needs no offsets, so like any other empty input they may be NULL
+        let offsets = if count == 0 && offsets.is_null() {
+            &[]
+        } else {
+            let len = count.checked_add(1).ok_or(Error::Length { len: count })?;
+            input_slice(offsets, len)?
+        };
+        let items = split_coords(coords.as_slice()?, offsets)?;
+        get_precision(precision).ok_or(Error::Precision(precision))?;
+        Ok(assemble_encoded(encode_items(items, precision)?))
+    });
+    match result {
+        Ok(batch) => {
+            out.write(batch);
+            report(Ok(()))
+        }
+        Err(e) => {
+            out.write(EncodedBatch::empty());
+            report(Err(e))
+        }
+    }
+}
+
+/// Convert a batch of coordinate sequences into Polylines, in a single call
+///
+/// Callers must pass five arguments:
+///
+/// - a [Struct](struct.ExternalArray.html) holding the lon, lat coordinates of every sequence, one after another
+/// - a pointer to an array of `count + 1` offsets (`size_t*`): the coordinates of sequence `i` are
+///   `coords[offsets[i]..offsets[i + 1]]`. Offsets must be non-decreasing, and may not exceed the length of `coords`.
+///   The pointer may be `NULL` if `count` is `0`
+/// - the number of sequences, as a `size_t`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a pointer to an [EncodedBatch](struct.EncodedBatch.html), which will receive the results
+///
+/// Each sequence is encoded independently: a sequence which fails to encode has no bytes in the output,
+/// and its failure is recorded in the `statuses` array, leaving the rest of the batch unaffected.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html) describing failures which affect the whole batch,
+/// such as an invalid precision, or an `InvalidOffset` status whose `position` is the index of the first bad offset.
+/// In that case `out` will hold an empty batch.
+///
+/// Implementations calling this function **must** call [`drop_encoded_batch`](fn.drop_encoded_batch.html)
+/// with the batch written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encode_coordinates_batch(
+    coords: ExternalArray,
+    offsets: *const libc::size_t,
+    count: libc::size_t,
+    precision: u32,
+    out: *mut EncodedBatch,
+) -> PolylineResult {
+    encode_batch(coords, offsets, count, precision, out, encode_items)
+}
+
+/// Free [EncodedBatch](struct.EncodedBatch.html) memory which Rust has allocated across the FFI boundary
+#[no_mangle]
+pub extern "C" fn drop_encoded_batch(batch: EncodedBatch) {
+    let _ = catch_panic(|| {
+        drop(batch);
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PolylineStatus;
+    use std::slice;
+
+    #[test]
+    fn test_decode_batch() {
+        let items: [&[u8]; 4] = [
+            b"_ibE_seK_seK_seK",
+            b"_ib E_seK",
+            b"",
+            b"_p~iF~ps|U_ulLnnqC_mqNvxq`@",
+        ];
+        let pls: Vec<*const u8> = items.iter().map(|i| i.as_ptr()).collect();
+        let lens: Vec<usize> = items.iter().map(|i| i.len()).collect();
+        let mut out = DecodedBatch::empty();
+        let res = unsafe { decode_polylines_batch(pls.as_ptr(), lens.as_ptr(), 4, 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        assert_eq!(out.count, 4);
+        let offsets = unsafe { slice::from_raw_parts(out.offsets, 5) };
+        assert_eq!(offsets, [0, 2, 2, 2, 5]);
+        let statuses = unsafe { slice::from_raw_parts(out.statuses, 4) };
+        let statuses: Vec<_> = statuses.iter().map(|s| (s.status, s.position)).collect();
+        assert_eq!(
+            statuses,
+            [
+                (PolylineStatus::Ok, 0),
+                (PolylineStatus::InvalidCharacter, 3),
+                (PolylineStatus::Ok, 0),
+                (PolylineStatus::Ok, 0)
+            ]
+        );
+        let coords = unsafe { slice::from_raw_parts(out.coords.data.cast::<[f64; 2]>(), 5) };
+        assert_eq!(coords[..2], [[2.0, 1.0], [4.0, 3.0]]);
+        assert_eq!(coords[2], [-120.2, 38.5]);
+        drop_decoded_batch(out);
+    }
+
+    #[test]
+    fn test_decode_batch_failures() {
+        let pls = [ptr::null()];
+        let lens = [4];
+        let mut out = DecodedBatch::empty();
+        let res = unsafe { decode_polylines_batch(pls.as_ptr(), lens.as_ptr(), 1, 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        let status = unsafe { *out.statuses };
+        assert_eq!(status.status, PolylineStatus::NullPointer);
+        drop_decoded_batch(out);
+
+        let mut out = DecodedBatch::empty();
+        let res = unsafe { decode_polylines_batch(pls.as_ptr(), lens.as_ptr(), 1, 10, &mut out) };
+        assert_eq!(res.status, PolylineStatus::InvalidPrecision);
+        assert_eq!(out.count, 0);
+        let res = unsafe { decode_polylines_batch(ptr::null(), lens.as_ptr(), 1, 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+
+        // an empty batch needs no input arrays
+        let res = unsafe { decode_polylines_batch(ptr::null(), ptr::null(), 0, 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        assert_eq!(unsafe { *out.offsets }, 0);
+        drop_decoded_batch(out);
+    }
+
+    #[test]
+    fn test_encode_batch() {
+        let coords: ExternalArray = vec![
+            [2.0, 1.0],
+            [4.0, 3.0],
+            [2.0, 91.0],
+            [-120.2, 38.5],
+            [-120.95, 40.7],
+            [-126.453, 43.252],
+        ]
+        .into();
+        let offsets = [0, 2, 3, 3, 6];
+        let mut out = EncodedBatch::empty();
+        let res = unsafe { encode_coordinates_batch(coords, offsets.as_ptr(), 4, 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        assert_eq!(out.count, 4);
+        let data = unsafe { slice::from_raw_parts(out.data.cast::<u8>(), out.len) };
+        let out_offsets = unsafe { slice::from_raw_parts(out.offsets, 5) };
+        let items: Vec<_> = out_offsets
+            .windows(2)
+            .map(|w| std::str::from_utf8(&data[w[0]..w[1]]).unwrap())
+            .collect();
+        assert_eq!(
+            items,
+            ["_ibE_seK_seK_seK", "", "", "_p~iF~ps|U_ulLnnqC_mqNvxq`@"]
+        );
+        let statuses = unsafe { slice::from_raw_parts(out.statuses, 4) };
+        assert_eq!(statuses[1].status, PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(statuses[1].position, 0);
+        assert_eq!(statuses[2], PolylineResult::ok());
+        drop_encoded_batch(out);
+    }
+
+    #[test]
+    fn test_encode_batch_bad_offsets() {
+        for (offsets, idx) in [([0, 2, 1], 2), ([0, 1, 3], 2), ([1, 0, 2], 1)] {
+            let coords: ExternalArray = vec![[2.0, 1.0], [4.0, 3.0]].into();
+            let mut out = EncodedBatch::empty();
+            let res = unsafe { encode_coordinates_batch(coords, offsets.as_ptr(), 2, 5, &mut out) };
+            assert_eq!(res.status, PolylineStatus::InvalidOffset);
+            assert_eq!(res.position, idx);
+            assert!(out.data.is_null());
+        }
+    }
+
+    #[test]
+    fn test_encode_empty_batch() {
+        for offsets in [[0].as_ptr(), ptr::null()] {
+            let coords: ExternalArray = vec![].into();
+            let mut out = EncodedBatch::empty();
+            let res = unsafe { encode_coordinates_batch(coords, offsets, 0, 5, &mut out) };
+            assert_eq!(res, PolylineResult::ok());
+            assert_eq!((out.count, out.len), (0, 0));
+            drop_encoded_batch(out);
+        }
+        // offsets are still required for a non-empty batch
+        let coords: ExternalArray = vec![].into();
+        let mut out = EncodedBatch::empty();
+        let res = unsafe { encode_coordinates_batch(coords, ptr::null(), 1, 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+    }
+}
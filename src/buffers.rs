@@ -0,0 +1,263 @@
+//! Entry points which write their output into caller-provided buffers, instead of allocating
+
+use crate::error::{catch_panic, report, update_last_error, Error, PolylineResult};
+use crate::{check_len, codec, get_precision, str_from_ptr, ExternalArray};
+use libc::c_char;
+use std::slice;
+
+// Borrow a caller-provided output buffer of capacity elements.
+// A NULL buffer is allowed if its capacity is 0, so callers can query the required size
+pub(crate) unsafe fn out_slice<'a, T>(buf: *mut T, capacity: usize) -> Result<&'a mut [T], Error> {
+    if capacity == 0 {
+        return Ok(&mut []);
+    }
+    if buf.is_null() {
+        return Err(Error::NullPointer);
+    }
+    check_len::<T>(capacity)?;
+    Ok(slice::from_raw_parts_mut(buf, capacity))
+}
+
+// Decode a Polyline into buf, returning the number of coordinates it contains
+fn decode_into(incoming: &str, precision: u32, buf: &mut [[f64; 2]]) -> Result<usize, Error> {
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    let mut len = 0;
+    for coord in codec::coords(incoming.as_bytes(), precision) {
+        let coord = coord?;
+        if let Some(slot) = buf.get_mut(len) {
+            *slot = coord;
+        }
+        len += 1;
+    }
+    if len > buf.len() {
+        return Err(Error::BufferTooSmall { required: len });
+    }
+    Ok(len)
+}
+
+/// Convert a Polyline into coordinates, writing them into a caller-provided buffer
+///
+/// Callers must pass five arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a pointer to a `double` buffer, which will receive lon, lat pairs: `[2.0, 1.0, 4.0, 3.0]`
+/// - the capacity of the buffer, in coordinate **pairs**. Its type must be `size_t`
+/// - a pointer to a `size_t`, which will receive the number of coordinate pairs written
+///
+/// This function does not allocate. If the buffer is too small, the status will be `BufferTooSmall`,
+/// and the required capacity (in coordinate pairs) will be written to `out_len`.
+/// The buffer pointer may be `NULL` if its capacity is `0`, so callers can query the required capacity up front.
+/// On any other failure, `out_len` will be set to `0`. The contents of the buffer are unspecified after a failure.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decode_polyline_into(
+    pl: *const c_char,
+    precision: u32,
+    out_buf: *mut f64,
+    out_capacity: libc::size_t,
+    out_len: *mut libc::size_t,
+) -> PolylineResult {
+    if out_len.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let incoming = str_from_ptr(pl)?;
+        let buf = out_slice(out_buf.cast::<[f64; 2]>(), out_capacity)?;
+        decode_into(incoming, precision, buf)
+    });
+    out_len.write(match result {
+        Ok(len) | Err(Error::BufferTooSmall { required: len }) => len,
+        Err(_) => 0,
+    });
+    report(result.map(|_| ()))
+}
+
+// Encode coordinates into buf, followed by a NUL terminator, returning the encoded length
+fn encode_into(coords: &[[f64; 2]], precision: u32, buf: &mut [u8]) -> Result<usize, Error> {
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    let mut len = 0;
+    codec::encode_coords(coords, precision, &mut |byte| {
+        if let Some(slot) = buf.get_mut(len) {
+            *slot = byte;
+        }
+        len += 1;
+    })?;
+    match buf.get_mut(len) {
+        Some(slot) => *slot = 0,
+        None => return Err(Error::BufferTooSmall { required: len + 1 }),
+    }
+    Ok(len)
+}
+
+/// Convert coordinates into a Polyline, writing it into a caller-provided buffer
+///
+/// Callers must pass five arguments:
+///
+/// - a [Struct](struct.ExternalArray.html) with two fields:
+///     - `data`, a void pointer to an array of floating-point lon, lat coordinates: `[[2.0, 1.0]]`
+///     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a pointer to a `char` buffer, which will receive the `NUL`-terminated Polyline
+/// - the capacity of the buffer in bytes, including space for the `NUL` terminator. Its type must be `size_t`
+/// - a pointer to a `size_t`, which will receive the number of bytes written, excluding the `NUL` terminator
+///
+/// This function does not allocate. If the buffer is too small, the status will be `BufferTooSmall`,
+/// and the required capacity (including the `NUL` terminator) will be written to `out_len`.
+/// [`encoded_length_upper_bound`](fn.encoded_length_upper_bound.html) can be used to size the buffer up front.
+/// On any other failure, `out_len` will be set to `0`. The contents of the buffer are unspecified after a failure.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encode_coordinates_into(
+    coords: ExternalArray,
+    precision: u32,
+    out_buf: *mut c_char,
+    out_capacity: libc::size_t,
+    out_len: *mut libc::size_t,
+) -> PolylineResult {
+    if out_len.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let buf = out_slice(out_buf.cast::<u8>(), out_capacity)?;
+        encode_into(coords.as_slice()?, precision, buf)
+    });
+    out_len.write(match result {
+        Ok(len) | Err(Error::BufferTooSmall { required: len }) => len,
+        Err(_) => 0,
+    });
+    report(result.map(|_| ()))
+}
+
+// The longest possible encoding of len coordinates, including a NUL terminator
+fn length_upper_bound(len: usize, precision: u32) -> Result<usize, Error> {
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    let factor = codec::factor(precision);
+    // the largest deltas are between the extremes of each axis
+    let per_coord = codec::max_value_len(180 * factor) + codec::max_value_len(360 * factor);
+    len.checked_mul(per_coord)
+        .and_then(|bytes| bytes.checked_add(1))
+        .ok_or(Error::Length { len })
+}
+
+/// Calculate the size of a buffer which can hold the encoding of any `len` coordinates
+///
+/// Callers must pass two arguments:
+///
+/// - the number of coordinates to be encoded, as a `size_t`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+///
+/// Returns a capacity in bytes, including space for the `NUL` terminator, which is suitable for
+/// passing to [`encode_coordinates_into`](fn.encode_coordinates_into.html).
+/// Returns `0` if the precision is invalid or the size would overflow a `size_t`.
+#[no_mangle]
+pub extern "C" fn encoded_length_upper_bound(len: libc::size_t, precision: u32) -> libc::size_t {
+    update_last_error(catch_panic(|| length_upper_bound(len, precision))).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PolylineStatus;
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    #[test]
+    fn test_decode_into() {
+        let input = CString::new("_ibE_seK_seK_seK").unwrap();
+        let mut buf = [0.0; 4];
+        let mut len = 0;
+        let res = unsafe { decode_polyline_into(input.as_ptr(), 5, buf.as_mut_ptr(), 2, &mut len) };
+        assert_eq!(res.status, PolylineStatus::Ok);
+        assert_eq!(len, 2);
+        assert_eq!(buf, [2.0, 1.0, 4.0, 3.0]);
+    }
+
+    #[test]
+    fn test_decode_into_too_small() {
+        let input = CString::new("_ibE_seK_seK_seK").unwrap();
+        let mut len = 0;
+        // query the required capacity
+        let res = unsafe { decode_polyline_into(input.as_ptr(), 5, ptr::null_mut(), 0, &mut len) };
+        assert_eq!(res.status, PolylineStatus::BufferTooSmall);
+        assert_eq!(len, 2);
+        let mut buf = [0.0; 2];
+        let res = unsafe { decode_polyline_into(input.as_ptr(), 5, buf.as_mut_ptr(), 1, &mut len) };
+        assert_eq!(res.status, PolylineStatus::BufferTooSmall);
+        assert_eq!(len, 2);
+    }
+
+    #[test]
+    fn test_encode_into() {
+        let coords: ExternalArray = vec![[2.0, 1.0], [4.0, 3.0]].into();
+        let mut buf = [1 as c_char; 17];
+        let mut len = 0;
+        let res = unsafe { encode_coordinates_into(coords, 5, buf.as_mut_ptr(), 17, &mut len) };
+        assert_eq!(res.status, PolylineStatus::Ok);
+        assert_eq!(len, 16);
+        let encoded = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        assert_eq!(encoded.to_str().unwrap(), "_ibE_seK_seK_seK");
+    }
+
+    #[test]
+    fn test_encode_into_too_small() {
+        let mut len = 0;
+        let mut buf = [1 as c_char; 16];
+        for capacity in [0, 8, 16] {
+            let coords: ExternalArray = vec![[2.0, 1.0], [4.0, 3.0]].into();
+            let res =
+                unsafe { encode_coordinates_into(coords, 5, buf.as_mut_ptr(), capacity, &mut len) };
+            assert_eq!(res.status, PolylineStatus::BufferTooSmall);
+            assert_eq!(len, 17);
+        }
+        let coords: ExternalArray = vec![[2.0, 91.0]].into();
+        let res = unsafe { encode_coordinates_into(coords, 5, buf.as_mut_ptr(), 16, &mut len) };
+        assert_eq!(res.status, PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(len, 0);
+    }
+
+    #[test]
+    fn test_encoded_length_upper_bound() {
+        assert_eq!(encoded_length_upper_bound(0, 5), 1);
+        assert_eq!(encoded_length_upper_bound(1, 10), 0);
+        assert_eq!(encoded_length_upper_bound(usize::MAX, 5), 0);
+        let extremes = vec![[-180.0, -90.0], [180.0, 90.0], [-180.0, -90.0]];
+        for precision in 0..=crate::MAX_PRECISION {
+            let bound = encoded_length_upper_bound(extremes.len(), precision);
+            let mut buf = vec![0 as c_char; bound];
+            let mut len = 0;
+            let coords: ExternalArray = extremes.clone().into();
+            let res = unsafe {
+                encode_coordinates_into(coords, precision, buf.as_mut_ptr(), bound, &mut len)
+            };
+            assert_eq!(res.status, PolylineStatus::Ok);
+            assert!(len < bound);
+        }
+    }
+
+    #[test]
+    fn test_decode_into_failures() {
+        let input = CString::new("_ibE_seK_seK").unwrap();
+        let mut buf = [0.0; 4];
+        let mut len = 99;
+        let res = unsafe { decode_polyline_into(input.as_ptr(), 5, buf.as_mut_ptr(), 2, &mut len) };
+        assert_eq!(res.status, PolylineStatus::TruncatedInput);
+        assert_eq!(res.position, 8);
+        assert_eq!(len, 0);
+        let res = unsafe { decode_polyline_into(input.as_ptr(), 5, ptr::null_mut(), 2, &mut len) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        let res = unsafe {
+            decode_polyline_into(input.as_ptr(), 5, buf.as_mut_ptr(), 2, ptr::null_mut())
+        };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+    }
+}
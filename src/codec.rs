@@ -0,0 +1,365 @@
+//! Allocation-free Polyline encoding and decoding, operating on scaled integer coordinates
+//!
+//! These follow the same rules as the `polyline` crate, except that the decoder also rejects a final value
+//! which is missing its terminating chunk.
+
+use crate::error::Error;
+use polyline::errors::PolylineError;
+
+const MIN_LONGITUDE: f64 = -180.0;
+const MAX_LONGITUDE: f64 = 180.0;
+const MIN_LATITUDE: f64 = -90.0;
+const MAX_LATITUDE: f64 = 90.0;
+
+// The scale factor for a validated precision
+pub(crate) fn factor(precision: u32) -> i64 {
+    10i64.pow(precision)
+}
+
+// Scale a coordinate value, rounding in the same way as the polyline crate
+pub(crate) fn scale(n: f64, factor: i64) -> i64 {
+    (n * factor as f64).round() as i64
+}
+
+// Check that a [lon, lat] pair at index idx of the input is in range, in the same order as the polyline crate
+pub(crate) fn check_coord(coord: [f64; 2], idx: usize) -> Result<(), Error> {
+    let [lon, lat] = coord;
+    if !(MIN_LATITUDE..=MAX_LATITUDE).contains(&lat) {
+        return Err(PolylineError::LatitudeCoordError { coord: lat, idx }.into());
+    }
+    if !(MIN_LONGITUDE..=MAX_LONGITUDE).contains(&lon) {
+        return Err(PolylineError::LongitudeCoordError { coord: lon, idx }.into());
+    }
+    Ok(())
+}
+
+// Encode a single value, passing each output byte to emit
+pub(crate) fn encode_value(delta: i64, emit: &mut impl FnMut(u8)) {
+    let mut value = delta << 1;
+    if value < 0 {
+        value = !value;
+    }
+    while value >= 0x20 {
+        emit(((0x20 | (value & 0x1f)) + 63) as u8);
+        value >>= 5;
+    }
+    emit((value + 63) as u8);
+}
+
+// The number of bytes needed to encode any delta in -max..=max
+pub(crate) const fn max_value_len(max: i64) -> usize {
+    let zigzag = (max as u64) << 1;
+    let bits = (u64::BITS - zigzag.leading_zeros()) as usize;
+    if bits == 0 {
+        1
+    } else {
+        bits.div_ceil(5)
+    }
+}
+
+// Encodes scaled coordinates as deltas from the previous coordinate
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PointEncoder {
+    lat: i64,
+    lon: i64,
+}
+
+impl PointEncoder {
+    // Continue encoding after a previously-encoded point
+    pub(crate) fn after(point: ScaledPoint) -> Self {
+        PointEncoder {
+            lat: point.lat,
+            lon: point.lon,
+        }
+    }
+
+    pub(crate) fn push_scaled(&mut self, lat: i64, lon: i64, emit: &mut impl FnMut(u8)) {
+        encode_value(lat - self.lat, emit);
+        encode_value(lon - self.lon, emit);
+        self.lat = lat;
+        self.lon = lon;
+    }
+
+    // Encode a [lon, lat] pair, at index idx of the input
+    pub(crate) fn push(
+        &mut self,
+        coord: [f64; 2],
+        idx: usize,
+        factor: i64,
+        emit: &mut impl FnMut(u8),
+    ) -> Result<(), Error> {
+        check_coord(coord, idx)?;
+        let [lon, lat] = coord;
+        self.push_scaled(scale(lat, factor), scale(lon, factor), emit);
+        Ok(())
+    }
+}
+
+// Encode [lon, lat] coordinates, passing each output byte to emit
+pub(crate) fn encode_coords(
+    coords: &[[f64; 2]],
+    precision: u32,
+    emit: &mut impl FnMut(u8),
+) -> Result<(), Error> {
+    let factor = factor(precision);
+    let mut encoder = PointEncoder::default();
+    for (idx, &coord) in coords.iter().enumerate() {
+        encoder.push(coord, idx, factor, emit)?;
+    }
+    Ok(())
+}
+
+// Accumulates the 5-bit chunks of a single value
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ValueDecoder {
+    shift: u32,
+    result: u64,
+}
+
+impl ValueDecoder {
+    // Whether some, but not all, of a value's chunks have been seen
+    pub(crate) fn is_partial(&self) -> bool {
+        self.shift > 0
+    }
+
+    // Feed one byte, found at index idx of the input.
+    // Returns the decoded value once its terminating chunk has been seen
+    pub(crate) fn push(&mut self, idx: usize, byte: u8) -> Result<Option<i64>, Error> {
+        if byte < 63 || self.shift > 64 - 5 {
+            return Err(PolylineError::DecodeError { idx }.into());
+        }
+        let chunk = byte - 63;
+        self.result |= ((chunk & 0x1f) as u64) << self.shift;
+        self.shift += 5;
+        if chunk >= 0x20 {
+            return Ok(None);
+        }
+        let result = self.result;
+        *self = ValueDecoder::default();
+        let value = if (result & 1) > 0 {
+            !(result >> 1)
+        } else {
+            result >> 1
+        } as i64;
+        Ok(Some(value))
+    }
+}
+
+// A decoded coordinate, scaled by the precision factor, with the offsets at which its values begin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ScaledPoint {
+    pub(crate) lat: i64,
+    pub(crate) lon: i64,
+    pub(crate) lat_idx: usize,
+    pub(crate) lon_idx: usize,
+}
+
+// Unscale a latitude whose value began at index idx of the input, checking that it's in range
+pub(crate) fn unscale_lat(lat: i64, factor: i64, idx: usize) -> Result<f64, Error> {
+    let lat = lat as f64 / factor as f64;
+    if !(MIN_LATITUDE..=MAX_LATITUDE).contains(&lat) {
+        return Err(PolylineError::LatitudeCoordError { coord: lat, idx }.into());
+    }
+    Ok(lat)
+}
+
+// Unscale a longitude whose value began at index idx of the input, checking that it's in range
+pub(crate) fn unscale_lon(lon: i64, factor: i64, idx: usize) -> Result<f64, Error> {
+    let lon = lon as f64 / factor as f64;
+    if !(MIN_LONGITUDE..=MAX_LONGITUDE).contains(&lon) {
+        return Err(PolylineError::LongitudeCoordError { coord: lon, idx }.into());
+    }
+    Ok(lon)
+}
+
+impl ScaledPoint {
+    // Convert to a [lon, lat] pair, checking that both values are in range
+    pub(crate) fn to_coord(self, factor: i64) -> Result<[f64; 2], Error> {
+        let lat = unscale_lat(self.lat, factor, self.lat_idx)?;
+        let lon = unscale_lon(self.lon, factor, self.lon_idx)?;
+        Ok([lon, lat])
+    }
+}
+
+// Iterate over the scaled coordinates of an encoded Polyline, without allocating
+#[derive(Debug, Clone)]
+pub(crate) struct ScaledPoints<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    lat: i64,
+    lon: i64,
+    // if set, each value is checked to be in range as soon as it's decoded, as the polyline crate does
+    factor: Option<i64>,
+    failed: bool,
+}
+
+impl<'a> ScaledPoints<'a> {
+    // Iterate without checking ranges, for callers which don't know the precision
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        ScaledPoints {
+            bytes,
+            pos: 0,
+            lat: 0,
+            lon: 0,
+            factor: None,
+            failed: false,
+        }
+    }
+
+    // Iterate, checking that every value is in range at the precision whose factor is given.
+    // Every checked decoder uses this, so that they all report the same failure for the same input
+    pub(crate) fn checked(bytes: &'a [u8], factor: i64) -> Self {
+        ScaledPoints {
+            factor: Some(factor),
+            ..ScaledPoints::new(bytes)
+        }
+    }
+
+    // Decode the value starting at the current position
+    fn next_value(&mut self) -> Result<i64, Error> {
+        let start = self.pos;
+        let mut decoder = ValueDecoder::default();
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            let idx = self.pos;
+            self.pos += 1;
+            if let Some(value) = decoder.push(idx, byte)? {
+                return Ok(value);
+            }
+        }
+        Err(Error::Truncated { idx: start })
+    }
+
+    fn next_point(&mut self) -> Result<ScaledPoint, Error> {
+        let lat_idx = self.pos;
+        // the sums can only overflow for out-of-range input, and saturating keeps them out of range
+        self.lat = self.lat.saturating_add(self.next_value()?);
+        if let Some(factor) = self.factor {
+            unscale_lat(self.lat, factor, lat_idx)?;
+        }
+        let lon_idx = self.pos;
+        if lon_idx == self.bytes.len() {
+            return Err(PolylineError::NoLongError { idx: lat_idx }.into());
+        }
+        self.lon = self.lon.saturating_add(self.next_value()?);
+        if let Some(factor) = self.factor {
+            unscale_lon(self.lon, factor, lon_idx)?;
+        }
+        Ok(ScaledPoint {
+            lat: self.lat,
+            lon: self.lon,
+            lat_idx,
+            lon_idx,
+        })
+    }
+}
+
+impl Iterator for ScaledPoints<'_> {
+    type Item = Result<ScaledPoint, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos == self.bytes.len() {
+            return None;
+        }
+        let point = self.next_point();
+        self.failed = point.is_err();
+        Some(point)
+    }
+}
+
+// Iterate over the [lon, lat] coordinates of an encoded Polyline, without allocating
+pub(crate) fn coords(
+    bytes: &[u8],
+    precision: u32,
+) -> impl Iterator<Item = Result<[f64; 2], Error>> + '_ {
+    let factor = factor(precision);
+    ScaledPoints::checked(bytes, factor).map(move |p| p?.to_coord(factor))
+}
+
+// Encode [lon, lat] coordinates as a Polyline, for use as a test fixture
+#[cfg(test)]
+pub(crate) fn encode_fixture(coords: &[[f64; 2]], precision: u32) -> String {
+    let mut encoded = String::new();
+    encode_coords(coords, precision, &mut |b| encoded.push(char::from(b))).unwrap();
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::LineString;
+    use polyline::{decode_polyline, encode_coordinates};
+
+    #[test]
+    fn test_matches_polyline_crate() {
+        let inputs = [
+            "_ibE_seK_seK_seK",
+            "_p~iF~ps|U_ulLnnqC_mqNvxq`@",
+            include!("../test_fixtures/berlin_decoded.rs"),
+        ];
+        for input in inputs {
+            for precision in [5, 6] {
+                let expected: Vec<[f64; 2]> = decode_polyline(input, precision)
+                    .unwrap()
+                    .0
+                    .iter()
+                    .map(|c| [c.x, c.y])
+                    .collect();
+                let decoded: Vec<[f64; 2]> = coords(input.as_bytes(), precision)
+                    .collect::<Result<_, _>>()
+                    .unwrap();
+                assert_eq!(decoded, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_matches_polyline_crate() {
+        let input: Vec<[f64; 2]> = include!("../test_fixtures/berlin.rs");
+        for precision in [0, 5, 6, 9] {
+            let expected = encode_coordinates(LineString::from(input.clone()), precision).unwrap();
+            assert_eq!(encode_fixture(&input, precision), expected);
+        }
+    }
+
+    #[test]
+    fn test_max_value_len() {
+        assert_eq!(max_value_len(0), 1);
+        // 15 (zigzag 30) fits in a single chunk, but 16 (zigzag 32) doesn't
+        assert_eq!(max_value_len(15), 1);
+        assert_eq!(max_value_len(16), 2);
+        for max in [1, 180 * 100_000, 360 * 1_000_000_000] {
+            let mut len = 0;
+            encode_value(-max, &mut |_| len += 1);
+            assert!(len <= max_value_len(max));
+            len = 0;
+            encode_value(max, &mut |_| len += 1);
+            assert_eq!(len, max_value_len(max));
+        }
+    }
+
+    #[test]
+    fn test_decoding_errors() {
+        let cases = [
+            ("_ib E_seK", PolylineError::DecodeError { idx: 3 }.into()),
+            ("_ibE_seK_seK", PolylineError::NoLongError { idx: 8 }.into()),
+            ("_ibE_seK_seK_se", Error::Truncated { idx: 12 }),
+            ("_ibE_s", Error::Truncated { idx: 4 }),
+            (
+                "~~~~~~~~~~~~~?_seK",
+                PolylineError::DecodeError { idx: 12 }.into(),
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = coords(input.as_bytes(), 5).find_map(Result::err).unwrap();
+            assert_eq!(err, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        // 91°, 2°
+        let err = coords(b"_c~uP_seK", 5).find_map(Result::err).unwrap();
+        assert_eq!(err.status(), crate::PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(err.axis(), crate::CoordinateAxis::Latitude);
+    }
+}
@@ -0,0 +1,407 @@
+//! Structured error reporting for the FFI entry points
+
+use libc::c_char;
+use polyline::errors::PolylineError;
+use std::any::Any;
+#[cfg(test)]
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::panic::{self, AssertUnwindSafe};
+use std::{fmt, ptr};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Status codes returned by the checked FFI entry points
+///
+/// `Ok` is always `0`, so callers can test for success with a simple comparison.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolylineStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// The input string was not valid UTF-8
+    InvalidUtf8,
+    /// An unsupported precision value was supplied
+    InvalidPrecision,
+    /// The input contained a character that is not part of the Polyline alphabet
+    InvalidCharacter,
+    /// The input ended part-way through a value, or a latitude had no longitude
+    TruncatedInput,
+    /// A coordinate fell outside the range `-90.0..=90.0` (latitude) or `-180.0..=180.0` (longitude)
+    CoordinateOutOfRange,
+    /// A required pointer argument was `NULL`
+    NullPointer,
+    /// A coordinate could not be encoded
+    EncodingError,
+    /// An array length was too large to be addressed
+    InvalidLength,
+    /// The library panicked. The panic was contained, and the call had no effect
+    Panic,
+    /// A caller-provided buffer was too small to hold the output
+    BufferTooSmall,
+    /// An offsets array was decreasing, or pointed past the end of its data
+    InvalidOffset,
+    /// A thread pool could not be created. Only returned when built with the `parallel` feature
+    ThreadPoolError,
+    /// A numeric argument was out of range, such as a negative tolerance
+    InvalidArgument,
+    /// Vincenty's formulae failed to converge, which can happen for nearly antipodal points
+    NoConvergence,
+}
+
+/// The coordinate axis on which a failure occurred
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateAxis {
+    /// The failure isn't specific to one axis
+    None = 0,
+    Latitude,
+    Longitude,
+}
+
+/// The outcome of a checked FFI call
+///
+/// `position` is the byte offset into the input string at which a decoding failure occurred,
+/// or the index of the coordinate at which an encoding or measuring failure occurred.
+/// It is `0` when `status` is `Ok`, or when the failure has no meaningful position.
+///
+/// `axis` identifies the offending axis of a `CoordinateOutOfRange` failure.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolylineResult {
+    pub status: PolylineStatus,
+    pub position: libc::size_t,
+    pub axis: CoordinateAxis,
+}
+
+impl PolylineResult {
+    pub(crate) fn ok() -> Self {
+        PolylineResult {
+            status: PolylineStatus::Ok,
+            position: 0,
+            axis: CoordinateAxis::None,
+        }
+    }
+}
+
+// Failures which can occur inside the library, before they're flattened for the FFI boundary
+#[derive(Debug, PartialEq)]
+pub(crate) enum Error {
+    Utf8 {
+        idx: usize,
+    },
+    Precision(u32),
+    Truncated {
+        idx: usize,
+    },
+    NullPointer,
+    Length {
+        len: usize,
+    },
+    Panic(String),
+    BufferTooSmall {
+        required: usize,
+    },
+    Offset {
+        idx: usize,
+    },
+    #[cfg(feature = "parallel")]
+    ThreadPool(String),
+    Argument(&'static str),
+    Convergence {
+        idx: usize,
+    },
+    Polyline(PolylineError),
+}
+
+impl Error {
+    pub(crate) fn status(&self) -> PolylineStatus {
+        match self {
+            Error::Utf8 { .. } => PolylineStatus::InvalidUtf8,
+            Error::Precision(_) => PolylineStatus::InvalidPrecision,
+            Error::Truncated { .. } => PolylineStatus::TruncatedInput,
+            Error::NullPointer => PolylineStatus::NullPointer,
+            Error::Length { .. } => PolylineStatus::InvalidLength,
+            Error::Panic(_) => PolylineStatus::Panic,
+            Error::BufferTooSmall { .. } => PolylineStatus::BufferTooSmall,
+            Error::Offset { .. } => PolylineStatus::InvalidOffset,
+            #[cfg(feature = "parallel")]
+            Error::ThreadPool(_) => PolylineStatus::ThreadPoolError,
+            Error::Argument(_) => PolylineStatus::InvalidArgument,
+            Error::Convergence { .. } => PolylineStatus::NoConvergence,
+            Error::Polyline(e) => match e {
+                PolylineError::DecodeError { .. } => PolylineStatus::InvalidCharacter,
+                PolylineError::NoLongError { .. } => PolylineStatus::TruncatedInput,
+                PolylineError::LatitudeCoordError { .. }
+                | PolylineError::LongitudeCoordError { .. } => PolylineStatus::CoordinateOutOfRange,
+                _ => PolylineStatus::EncodingError,
+            },
+        }
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        match self {
+            Error::Utf8 { idx }
+            | Error::Truncated { idx }
+            | Error::Offset { idx }
+            | Error::Convergence { idx } => *idx,
+            Error::Polyline(
+                PolylineError::DecodeError { idx }
+                | PolylineError::NoLongError { idx }
+                | PolylineError::LatitudeCoordError { idx, .. }
+                | PolylineError::LongitudeCoordError { idx, .. }
+                | PolylineError::CoordEncodingError { idx, .. },
+            ) => *idx,
+            _ => 0,
+        }
+    }
+
+    // Move the byte offset of a decoding failure, for input which began at offset by of a larger input
+    pub(crate) fn offset_by(self, by: usize) -> Self {
+        match self {
+            Error::Utf8 { idx } => Error::Utf8 { idx: idx + by },
+            Error::Truncated { idx } => Error::Truncated { idx: idx + by },
+            Error::Polyline(e) => Error::Polyline(match e {
+                PolylineError::DecodeError { idx } => PolylineError::DecodeError { idx: idx + by },
+                PolylineError::NoLongError { idx } => PolylineError::NoLongError { idx: idx + by },
+                PolylineError::LatitudeCoordError { coord, idx } => {
+                    PolylineError::LatitudeCoordError {
+                        coord,
+                        idx: idx + by,
+                    }
+                }
+                PolylineError::LongitudeCoordError { coord, idx } => {
+                    PolylineError::LongitudeCoordError {
+                        coord,
+                        idx: idx + by,
+                    }
+                }
+                e => e,
+            }),
+            e => e,
+        }
+    }
+
+    pub(crate) fn axis(&self) -> CoordinateAxis {
+        match self {
+            Error::Polyline(PolylineError::LatitudeCoordError { .. }) => CoordinateAxis::Latitude,
+            Error::Polyline(PolylineError::LongitudeCoordError { .. }) => CoordinateAxis::Longitude,
+            _ => CoordinateAxis::None,
+        }
+    }
+}
+
+// PolylineError isn't Clone, so we clone its variants by hand. This lets a failed handle keep reporting its failure
+impl Clone for Error {
+    fn clone(&self) -> Self {
+        match self {
+            Error::Utf8 { idx } => Error::Utf8 { idx: *idx },
+            Error::Precision(p) => Error::Precision(*p),
+            Error::Truncated { idx } => Error::Truncated { idx: *idx },
+            Error::NullPointer => Error::NullPointer,
+            Error::Length { len } => Error::Length { len: *len },
+            Error::Panic(msg) => Error::Panic(msg.clone()),
+            Error::BufferTooSmall { required } => Error::BufferTooSmall {
+                required: *required,
+            },
+            Error::Offset { idx } => Error::Offset { idx: *idx },
+            #[cfg(feature = "parallel")]
+            Error::ThreadPool(msg) => Error::ThreadPool(msg.clone()),
+            Error::Argument(msg) => Error::Argument(msg),
+            Error::Convergence { idx } => Error::Convergence { idx: *idx },
+            Error::Polyline(e) => Error::Polyline(match *e {
+                PolylineError::LongitudeCoordError { coord, idx } => {
+                    PolylineError::LongitudeCoordError { coord, idx }
+                }
+                PolylineError::LatitudeCoordError { coord, idx } => {
+                    PolylineError::LatitudeCoordError { coord, idx }
+                }
+                PolylineError::NoLongError { idx } => PolylineError::NoLongError { idx },
+                PolylineError::DecodeError { idx } => PolylineError::DecodeError { idx },
+                PolylineError::CoordEncodingError { coord, idx } => {
+                    PolylineError::CoordEncodingError { coord, idx }
+                }
+                // PolylineError is non-exhaustive, and every other current variant carries no data
+                _ => PolylineError::EncodeToCharError,
+            }),
+        }
+    }
+}
+
+impl From<PolylineError> for Error {
+    fn from(e: PolylineError) -> Self {
+        Error::Polyline(e)
+    }
+}
+
+impl From<&Error> for PolylineResult {
+    fn from(e: &Error) -> Self {
+        PolylineResult {
+            status: e.status(),
+            position: e.position(),
+            axis: e.axis(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Utf8 { idx } => write!(f, "invalid UTF-8 at byte {}", idx),
+            Error::Precision(p) => write!(f, "unsupported precision: {}", p),
+            Error::Truncated { idx } => write!(f, "unterminated value starting at index {}", idx),
+            Error::NullPointer => write!(f, "a required pointer argument was NULL"),
+            Error::Length { len } => write!(f, "array length {} is too large", len),
+            Error::Panic(msg) => write!(f, "internal panic: {}", msg),
+            Error::BufferTooSmall { required } => {
+                write!(f, "output buffer too small: {} required", required)
+            }
+            Error::Offset { idx } => write!(f, "invalid offset at index {}", idx),
+            #[cfg(feature = "parallel")]
+            Error::ThreadPool(msg) => write!(f, "couldn't create thread pool: {}", msg),
+            Error::Argument(msg) => write!(f, "invalid argument: {}", msg),
+            Error::Convergence { idx } => write!(
+                f,
+                "Vincenty's formulae failed to converge for the segment starting at index {}",
+                idx
+            ),
+            Error::Polyline(e) => e.fmt(f),
+        }
+    }
+}
+
+// Extract the message from a panic payload, which is almost always a &str or a String
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or_else(|| "unknown panic".to_string(), |msg| msg.to_string()),
+    }
+}
+
+// Run the body of an FFI function, turning a panic into an error instead of unwinding into the caller.
+// Nothing the closures touch is left in an inconsistent state by a panic, so asserting unwind safety is sound
+pub(crate) fn catch_panic<T, F>(f: F) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error>,
+{
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|e| Err(Error::Panic(panic_message(e))))
+}
+
+#[cfg(test)]
+thread_local! {
+    // Set by tests to make the shared decoding and encoding paths panic, so that real entry points can be checked
+    pub(crate) static INJECT_PANIC: Cell<bool> = const { Cell::new(false) };
+}
+
+// Panic if a test on this thread has asked for it
+#[cfg(test)]
+pub(crate) fn injected_panic() {
+    if INJECT_PANIC.with(Cell::get) {
+        panic!("injected panic");
+    }
+}
+
+// Record the outcome of an FFI call on the calling thread.
+// Every entry point passes its result through here exactly once, so success clears the previous message
+pub(crate) fn update_last_error<T>(result: Result<T, Error>) -> Result<T, Error> {
+    let msg = result
+        .as_ref()
+        .err()
+        .map(|e| CString::new(e.to_string()).unwrap_or_default());
+    // the thread-local may already have been destroyed if we're called during thread teardown
+    let _ = LAST_ERROR.try_with(|last| *last.borrow_mut() = msg);
+    result
+}
+
+// Record the outcome of a checked FFI call, and flatten it for the FFI boundary
+pub(crate) fn report(result: Result<(), Error>) -> PolylineResult {
+    match update_last_error(result) {
+        Ok(()) => PolylineResult::ok(),
+        Err(e) => (&e).into(),
+    }
+}
+
+/// Retrieve a description of the most recent failure on the calling thread
+///
+/// Every encoding or decoding call replaces the stored message, so this describes the most recent call only.
+/// Returns a `NUL`-terminated `char*`, or `NULL` if the most recent call succeeded.
+///
+/// The returned pointer is owned by the library, and remains valid until the next encoding or
+/// decoding call on the same thread. Callers **must not** pass it to [`drop_cstring`](fn.drop_cstring.html).
+#[no_mangle]
+pub extern "C" fn polyline_last_error_message() -> *const c_char {
+    LAST_ERROR
+        .try_with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(ptr::null(), |msg| msg.as_ptr())
+        })
+        .unwrap_or(ptr::null())
+}
+
+/// Retrieve the length in bytes of the most recent failure message on the calling thread
+///
+/// The length excludes the trailing `NUL`. Returns `0` if the most recent call succeeded.
+#[no_mangle]
+pub extern "C" fn polyline_last_error_length() -> libc::size_t {
+    LAST_ERROR
+        .try_with(|last| last.borrow().as_ref().map_or(0, |msg| msg.as_bytes().len()))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polyline_error_mapping() {
+        let err: Error = PolylineError::DecodeError { idx: 3 }.into();
+        let res: PolylineResult = (&err).into();
+        assert_eq!(res.status, PolylineStatus::InvalidCharacter);
+        assert_eq!(res.position, 3);
+
+        let err: Error = PolylineError::NoLongError { idx: 4 }.into();
+        assert_eq!(err.status(), PolylineStatus::TruncatedInput);
+        assert_eq!(err.position(), 4);
+        assert_eq!(err.axis(), CoordinateAxis::None);
+
+        let err: Error = PolylineError::LongitudeCoordError {
+            coord: 181.0,
+            idx: 2,
+        }
+        .into();
+        let res: PolylineResult = (&err).into();
+        assert_eq!(res.status, PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(res.position, 2);
+        assert_eq!(res.axis, CoordinateAxis::Longitude);
+    }
+
+    #[test]
+    fn test_last_error() {
+        let _ = update_last_error::<()>(Err(Error::Precision(12)));
+        let msg = unsafe { std::ffi::CStr::from_ptr(polyline_last_error_message()) };
+        assert_eq!(msg.to_str().unwrap(), "unsupported precision: 12");
+        assert_eq!(polyline_last_error_length(), msg.to_bytes().len());
+        // success clears the message
+        let _ = update_last_error(Ok(()));
+        assert!(polyline_last_error_message().is_null());
+        assert_eq!(polyline_last_error_length(), 0);
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        let res: Result<(), Error> = catch_panic(|| panic!("injected"));
+        assert_eq!(res, Err(Error::Panic("injected".to_string())));
+        let res: Result<(), Error> = catch_panic(|| panic!("injected {}", 2));
+        assert_eq!(res, Err(Error::Panic("injected 2".to_string())));
+        let res = report(catch_panic(|| -> Result<(), Error> { panic!("injected") }));
+        assert_eq!(res.status, PolylineStatus::Panic);
+        let msg = unsafe { std::ffi::CStr::from_ptr(polyline_last_error_message()) };
+        assert_eq!(msg.to_str().unwrap(), "internal panic: injected");
+    }
+}
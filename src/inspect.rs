@@ -0,0 +1,666 @@
+//! Entry points which inspect encoded Polylines, without decoding them into an array of coordinates
+
+use crate::codec::{self, factor, ScaledPoints, ValueDecoder};
+use crate::error::{catch_panic, report, Error, PolylineResult};
+use crate::MAX_PRECISION;
+use crate::{get_precision, input_slice, str_from_ptr, str_from_raw_parts, PolylineStatus};
+use libc::c_char;
+use polyline::errors::PolylineError;
+
+// Count the points in an encoded Polyline by finding the end of each value, applying the same checks as decoding
+fn count_points(bytes: &[u8]) -> Result<usize, Error> {
+    let mut decoder = ValueDecoder::default();
+    let mut values = 0;
+    // the offsets at which the current value, and the current latitude, began
+    let mut value_idx = 0;
+    let mut lat_idx = 0;
+    for (idx, &byte) in bytes.iter().enumerate() {
+        if !decoder.is_partial() {
+            value_idx = idx;
+        }
+        if decoder.push(idx, byte)?.is_some() {
+            if values % 2 == 0 {
+                lat_idx = value_idx;
+            }
+            values += 1;
+        }
+    }
+    if decoder.is_partial() {
+        return Err(Error::Truncated { idx: value_idx });
+    }
+    if values % 2 == 1 {
+        return Err(PolylineError::NoLongError { idx: lat_idx }.into());
+    }
+    Ok(values / 2)
+}
+
+/// Count the points in a Polyline, without decoding it
+///
+/// Callers must pass three arguments:
+///
+/// - a pointer to the Polyline bytes (`const uint8_t*`), which need not be `NUL`-terminated
+/// - the number of bytes, as a `size_t`
+/// - a pointer to a `size_t`, which will receive the number of points
+///
+/// The bytes must be valid UTF-8. This function does not allocate, and doesn't need a precision,
+/// so coordinates are not checked for range.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// On failure, `out_count` will be set to `0`.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_point_count(
+    pl: *const u8,
+    len: libc::size_t,
+    out_count: *mut libc::size_t,
+) -> PolylineResult {
+    if out_count.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| count_points(str_from_raw_parts(pl, len)?.as_bytes()));
+    out_count.write(*result.as_ref().unwrap_or(&0));
+    report(result.map(|_| ()))
+}
+
+// The last character of the Polyline alphabet. The decoder accepts anything from 63 upwards, but nothing above this is ever encoded
+const MAX_CHARACTER: u8 = 126;
+
+// Check that an encoded Polyline is well-formed, and optionally that its coordinates are in range,
+// reporting the first problem found
+fn validate(bytes: &[u8], precision: u32, check_bounds: bool) -> Result<(), Error> {
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    // check everything before the first character above the alphabet, which the decoder would accept
+    let end = bytes
+        .iter()
+        .position(|&b| b > MAX_CHARACTER)
+        .unwrap_or(bytes.len());
+    let result = if check_bounds {
+        codec::coords(&bytes[..end], precision).try_for_each(|coord| coord.map(|_| ()))
+    } else {
+        count_points(&bytes[..end]).map(|_| ())
+    };
+    match result {
+        // an earlier problem
+        Err(e) if e.status() != PolylineStatus::TruncatedInput => Err(e),
+        // the input only ended early because we stopped at the bad character
+        _ if end < bytes.len() => Err(PolylineError::DecodeError { idx: end }.into()),
+        result => result,
+    }
+}
+
+/// Check that a Polyline is well-formed, without decoding it
+///
+/// Callers must pass four arguments:
+///
+/// - a pointer to the Polyline bytes (`const uint8_t*`), which need not be `NUL`-terminated
+/// - the number of bytes, as a `size_t`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a `bool`: if `true`, every coordinate is also checked to be in the range `-90.0..=90.0` (latitude)
+///   and `-180.0..=180.0` (longitude)
+///
+/// Every byte must be in the range `63..=126`, every value must be terminated, and every latitude
+/// must have a longitude. This function does not allocate.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), whose `position` is the byte offset of the first problem found.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_validate(
+    pl: *const u8,
+    len: libc::size_t,
+    precision: u32,
+    check_bounds: bool,
+) -> PolylineResult {
+    report(catch_panic(|| {
+        validate(input_slice(pl, len)?, precision, check_bounds)
+    }))
+}
+
+// The approximate length of a degree of latitude, or of longitude at the equator
+const METRES_PER_DEGREE: f64 = 111_195.0;
+// The distance between consecutive points which we consider most plausible
+const TYPICAL_STEP_METRES: f64 = 50.0;
+// The most that the distances between points can change the odds of one precision over another
+const MAX_STEP_ODDS: f64 = 4.0;
+// The steps of a single Polyline aren't independent, so the evidence they provide stops growing after this many
+const MAX_EVIDENCE_STEPS: usize = 20;
+// Any Polyline with precision 5 is in range at precision 6, but one with precision 6 is only in range at precision 5
+// if it lies near 0°, 0°. So when both are in range, precision 5 is more likely by this much
+const PRECISION_5_ODDS: f64 = 2.0;
+// Coordinates which all lie within this fraction of each axis' range of 0°, 0° are implausible, and become more so
+// the closer they are
+const NULL_ISLAND_REACH: f64 = 0.01;
+
+// Find the largest absolute latitude and longitude in an encoded Polyline, as integers, checking that it's well-formed
+fn max_values(bytes: &[u8]) -> Result<(u64, u64), Error> {
+    ScaledPoints::new(bytes).try_fold((0, 0), |(max_lat, max_lon), point| {
+        let point = point?;
+        Ok((
+            max_lat.max(point.lat.unsigned_abs()),
+            max_lon.max(point.lon.unsigned_abs()),
+        ))
+    })
+}
+
+// The mean base-10 logarithm of the distance in metres between consecutive distinct points, when decoded using the
+// given precision, with the number of those distances. The Polyline must already have been checked
+fn log_steps(bytes: &[u8], precision: u32) -> (f64, usize) {
+    let factor = factor(precision) as f64;
+    let mut previous: Option<(f64, f64)> = None;
+    let (mut sum, mut steps) = (0.0, 0);
+    for point in ScaledPoints::new(bytes).map_while(Result::ok) {
+        let (lat, lon) = (point.lat as f64 / factor, point.lon as f64 / factor);
+        if let Some((prev_lat, prev_lon)) = previous.replace((lat, lon)) {
+            // approximate, as a planar distance with longitude scaled at the current latitude
+            let step = (lat - prev_lat).hypot((lon - prev_lon) * lat.to_radians().cos());
+            if step > 0.0 {
+                sum += (step * METRES_PER_DEGREE).log10();
+                steps += 1;
+            }
+        }
+    }
+    (if steps > 0 { sum / steps as f64 } else { 0.0 }, steps)
+}
+
+// Score a precision by how plausible the Polyline's coordinates would be when decoded using it, as a natural
+// logarithm of its relative odds. Higher is better
+fn score(bytes: &[u8], (max_lat, max_lon): (u64, u64), precision: u32) -> f64 {
+    let factor = factor(precision) as f64;
+    let mut score = if precision == 5 {
+        PRECISION_5_ODDS.ln()
+    } else {
+        0.0
+    };
+    // how far the coordinates reach from 0°, 0°, as a fraction of each axis' range
+    let reach = (max_lat as f64 / factor / 90.0).max(max_lon as f64 / factor / 180.0);
+    if reach > 0.0 {
+        score += (reach / NULL_ISLAND_REACH).min(1.0).ln();
+    }
+    // the closer the typical distance between points is to TYPICAL_STEP_METRES, the better, up to a decade away
+    let (mean_log_step, steps) = log_steps(bytes, precision);
+    if steps > 0 {
+        let distance = (mean_log_step - TYPICAL_STEP_METRES.log10()).abs().min(1.0);
+        let evidence = steps.min(MAX_EVIDENCE_STEPS) as f64 / MAX_EVIDENCE_STEPS as f64;
+        score -= MAX_STEP_ODDS.ln() * evidence * distance;
+    }
+    score
+}
+
+// Find the most plausible precision for an encoded Polyline, with its share of the total score
+fn guess_precision(bytes: &[u8]) -> Result<(u32, f64), Error> {
+    let max = max_values(bytes)?;
+    let in_range = |&precision: &u32| {
+        let factor = factor(precision) as f64;
+        max.0 as f64 / factor <= 90.0 && max.1 as f64 / factor <= 180.0
+    };
+    // precisions 5 and 6 are by far the most common, so higher precisions are only candidates if neither is in range
+    let mut candidates: Vec<u32> = [5, 6].into_iter().filter(in_range).collect();
+    if candidates.is_empty() {
+        candidates = (7..=MAX_PRECISION).filter(in_range).collect();
+    }
+    let scores: Vec<(u32, f64)> = candidates
+        .into_iter()
+        .map(|p| (p, score(bytes, max, p)))
+        .collect();
+    // ties go to the lowest precision
+    let Some(&(best, best_score)) = scores.iter().reduce(|best, candidate| {
+        if candidate.1 > best.1 {
+            candidate
+        } else {
+            best
+        }
+    }) else {
+        // out of range at every precision, so report the failure at the precision which is least likely to fail
+        let err = codec::coords(bytes, MAX_PRECISION).find_map(Result::err);
+        return Err(err.unwrap_or(Error::Precision(MAX_PRECISION)));
+    };
+    // relative to the best score, so that the best candidate contributes exactly 1
+    let total: f64 = scores
+        .iter()
+        .map(|&(_, score)| (score - best_score).exp())
+        .sum();
+    Ok((best, 1.0 / total))
+}
+
+/// Guess the precision of a Polyline
+///
+/// Callers must pass three arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - a pointer to an unsigned 32-bit `int`, which will receive the most likely precision
+/// - a pointer to a `double`, which will receive a confidence value between `0.0` and `1.0`
+///
+/// Precisions 5 and 6 are the candidates, and a precision which would put a coordinate out of range is ruled out.
+/// Only if both are ruled out are precisions 7 up to [`polyline_max_precision`](fn.polyline_max_precision.html)
+/// considered instead, and lower precisions are never guessed. Of the remaining candidates, the guess prefers precision 5,
+/// since a Polyline with precision 6 is only in range at precision 5 if it lies close to 0°, 0°. It also prefers
+/// precisions which don't put every coordinate very close to 0°, 0°, and those which put the typical distance between
+/// consecutive points closer to 50 metres. The confidence is the best candidate's share of the total score.
+///
+/// This is a heuristic. Ruling a precision out is reliable, so a Polyline with precision 6 far from 0°, 0° is guessed
+/// with a confidence of `1.0`. But one with precision 5 can only be told apart from one with precision 6 by its position
+/// and the distances between its points, so unless it lies close to 0°, 0° it's guessed with a confidence below `0.9`.
+/// The distances between points can change the odds by a factor of at most 4, so precision 5 Polylines whose points
+/// are more than around 300 metres apart, or precision 6 Polylines near 0°, 0° whose points are less than around
+/// 30 metres apart, may be guessed wrongly, but with low confidence. A Polyline with no points is guessed to have a
+/// precision of 5, with low confidence.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// The status is `CoordinateOutOfRange` if a coordinate is out of range at every precision.
+/// On failure, `out_precision` and `out_confidence` will be set to `0`.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_guess_precision(
+    pl: *const c_char,
+    out_precision: *mut u32,
+    out_confidence: *mut f64,
+) -> PolylineResult {
+    if out_precision.is_null() || out_confidence.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| guess_precision(str_from_ptr(pl)?.as_bytes()));
+    let (precision, confidence) = *result.as_ref().unwrap_or(&(0, 0.0));
+    out_precision.write(precision);
+    out_confidence.write(confidence);
+    report(result.map(|_| ()))
+}
+
+/// The extent of a Polyline's coordinates, in degrees
+///
+/// `west` and `east` are the minimum and maximum longitudes, and `south` and `north` the minimum and maximum latitudes.
+/// If the box crosses the antimeridian, `west` is greater than `east`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+impl BoundingBox {
+    // The box written on failure
+    fn empty() -> Self {
+        BoundingBox {
+            west: 0.0,
+            south: 0.0,
+            east: 0.0,
+            north: 0.0,
+        }
+    }
+}
+
+// The minimum and maximum of a sequence of scaled values
+#[derive(Debug, Clone, Copy)]
+struct Extent {
+    min: i64,
+    max: i64,
+}
+
+impl Extent {
+    fn new(value: i64) -> Self {
+        Extent {
+            min: value,
+            max: value,
+        }
+    }
+
+    fn include(&mut self, value: i64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn span(self) -> i64 {
+        self.max - self.min
+    }
+}
+
+// Find the bounding box of an encoded Polyline in a single pass, working in scaled integers so that no rounding occurs.
+// To detect boxes crossing the antimeridian, longitudes are also tracked after shifting them into 0..360: a box in that
+// frame may cross ±180, but not 0. Whichever frame gives the narrower span wins, with ties going to the unshifted one.
+// This is exact unless the largest gap between neighbouring longitudes contains neither 0 nor ±180, in which case
+// the box can be wider than necessary. Finding that gap would mean sorting every longitude
+fn bbox(bytes: &[u8], precision: u32, antimeridian: bool) -> Result<BoundingBox, Error> {
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    let factor = factor(precision);
+    let half_turn = 180 * factor;
+    let shift = |lon: i64| if lon < 0 { lon + 2 * half_turn } else { lon };
+    let mut extents: Option<[Extent; 3]> = None;
+    // check the range of every coordinate
+    for point in ScaledPoints::checked(bytes, factor) {
+        let point = point?;
+        let values = [point.lat, point.lon, shift(point.lon)];
+        match extents.as_mut() {
+            Some(extents) => {
+                for (extent, value) in extents.iter_mut().zip(values) {
+                    extent.include(value);
+                }
+            }
+            None => extents = Some(values.map(Extent::new)),
+        }
+    }
+    let [lat, lon, shifted] =
+        extents.ok_or(Error::Argument("an empty Polyline has no bounding box"))?;
+    let (west, east) = if antimeridian && shifted.span() < lon.span() {
+        let unshift = |lon: i64| {
+            if lon > half_turn {
+                lon - 2 * half_turn
+            } else {
+                lon
+            }
+        };
+        (unshift(shifted.min), unshift(shifted.max))
+    } else {
+        (lon.min, lon.max)
+    };
+    let scale = |value: i64| value as f64 / factor as f64;
+    Ok(BoundingBox {
+        west: scale(west),
+        south: scale(lat.min),
+        east: scale(east),
+        north: scale(lat.max),
+    })
+}
+
+/// Find the bounding box of a Polyline, without decoding it
+///
+/// Callers must pass five arguments:
+///
+/// - a pointer to the Polyline bytes (`const uint8_t*`), which need not be `NUL`-terminated
+/// - the number of bytes, as a `size_t`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a `bool`: if `true`, and a box crossing the antimeridian is narrower, the box crosses it, and `west` will be
+///   greater than `east`. If `false`, `west` and `east` are the smallest and largest longitudes
+/// - a pointer to a [BoundingBox](struct.BoundingBox.html), which will receive the box
+///
+/// The crossing box runs between the smallest and largest longitudes when they are measured eastwards from 0°,
+/// so it never contains 0°. This finds the narrowest box unless the points leave a wider gap between their longitudes
+/// somewhere other than around 0° or ±180°: longitudes of `30`, `150`, `-150` and `-30` give a box from `-150` to `150`,
+/// although one from `-30` to `-150` is narrower.
+///
+/// The bytes must be valid UTF-8. Every coordinate is checked to be in range, and this function does not allocate.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// The status is `InvalidArgument` if the Polyline has no points.
+/// On failure, every field of `out_bbox` will be set to `0`.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_bbox(
+    pl: *const u8,
+    len: libc::size_t,
+    precision: u32,
+    antimeridian: bool,
+    out_bbox: *mut BoundingBox,
+) -> PolylineResult {
+    if out_bbox.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        bbox(
+            str_from_raw_parts(pl, len)?.as_bytes(),
+            precision,
+            antimeridian,
+        )
+    });
+    out_bbox.write(*result.as_ref().unwrap_or(&BoundingBox::empty()));
+    report(result.map(|_| ()))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codec, PolylineStatus};
+    use std::ffi::CString;
+    use std::ptr;
+
+    #[test]
+    fn test_point_count() {
+        let inputs = [
+            "",
+            "_ibE_seK_seK_seK",
+            "_p~iF~ps|U_ulLnnqC_mqNvxq`@",
+            include!("../test_fixtures/berlin_decoded.rs"),
+        ];
+        for input in inputs {
+            let expected = codec::coords(input.as_bytes(), 5).count();
+            let mut count = usize::MAX;
+            let res = unsafe { polyline_point_count(input.as_ptr(), input.len(), &mut count) };
+            assert_eq!(res, PolylineResult::ok());
+            assert_eq!(count, expected);
+        }
+    }
+
+    #[test]
+    fn test_point_count_failures() {
+        // the same failures as decoding
+        for input in ["_ib E_seK", "_ibE_seK_seK", "_ibE_seK_seK_se", "_ibE_s"] {
+            let expected = codec::coords(input.as_bytes(), 5).find_map(Result::err);
+            assert_eq!(count_points(input.as_bytes()).err(), expected, "{}", input);
+        }
+        let mut count = 1;
+        let res = unsafe { polyline_point_count(b"_ibE_s".as_ptr(), 6, &mut count) };
+        assert_eq!(res.status, PolylineStatus::TruncatedInput);
+        assert_eq!(count, 0);
+        let res = unsafe { polyline_point_count(ptr::null(), 1, &mut count) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        let res = unsafe { polyline_point_count(ptr::null(), 0, &mut count) };
+        assert_eq!(res, PolylineResult::ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        let input: &str = include!("../test_fixtures/berlin_decoded.rs");
+        for check_bounds in [false, true] {
+            let res = unsafe { polyline_validate(input.as_ptr(), input.len(), 5, check_bounds) };
+            assert_eq!(res, PolylineResult::ok());
+        }
+        // 91°, 2° is only rejected when checking bounds
+        assert_eq!(validate(b"_c~uP_seK", 5, false), Ok(()));
+        let err = validate(b"_c~uP_seK", 5, true).unwrap_err();
+        assert_eq!(err.status(), PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(validate(b"~?~?", 5, false), Ok(()));
+        assert_eq!(validate(b"", 10, false), Err(Error::Precision(10)));
+    }
+
+    #[test]
+    fn test_validate_failures() {
+        let cases: [(&[u8], PolylineStatus, usize); 6] = [
+            (b"_ibE_seK\x7f", PolylineStatus::InvalidCharacter, 8),
+            (b"_ibE_s\x7feK", PolylineStatus::InvalidCharacter, 6),
+            (b"_ibE_s\xc3\xa9", PolylineStatus::InvalidCharacter, 6),
+            (b"_ib E_seK\x7f", PolylineStatus::InvalidCharacter, 3),
+            (b"_ibE_seK_seK", PolylineStatus::TruncatedInput, 8),
+            (
+                b"~~~~~~~~~~~~~?_seK\x7f",
+                PolylineStatus::InvalidCharacter,
+                12,
+            ),
+        ];
+        for (input, status, position) in cases {
+            let res = unsafe { polyline_validate(input.as_ptr(), input.len(), 5, true) };
+            assert_eq!(
+                (res.status, res.position),
+                (status, position),
+                "{:?}",
+                input
+            );
+        }
+        let res = unsafe { polyline_validate(ptr::null(), 1, 5, false) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        let res = unsafe { polyline_validate(ptr::null(), 0, 5, true) };
+        assert_eq!(res, PolylineResult::ok());
+    }
+
+    #[test]
+    fn test_guess_precision() {
+        let input: Vec<[f64; 2]> = include!("../test_fixtures/berlin.rs");
+        for expected in [5, 6, 7] {
+            let encoded = CString::new(codec::encode_fixture(&input, expected)).unwrap();
+            let (mut precision, mut confidence) = (0, 0.0);
+            let res = unsafe {
+                polyline_guess_precision(encoded.as_ptr(), &mut precision, &mut confidence)
+            };
+            assert_eq!(res, PolylineResult::ok());
+            assert_eq!(precision, expected);
+            if expected == 6 {
+                // precision 5 would put it out of range
+                assert_eq!(confidence, 1.0);
+            } else {
+                // precision 5 or 8 would put it in range too
+                assert!(confidence > 0.5 && confidence < 0.9, "{}", confidence);
+            }
+        }
+        assert_eq!(guess_precision(b""), Ok((5, 2.0 / 3.0)));
+    }
+
+    #[test]
+    fn test_guess_precision_examples() {
+        // the examples from the polyline crate's documentation
+        let (precision, confidence) = guess_precision(b"_p~iF~ps|U_ulLnnqC_mqNvxq`@").unwrap();
+        assert_eq!(precision, 5);
+        // its points are hundreds of kilometres apart, which is implausible at either precision
+        assert!(confidence < 0.7, "{}", confidence);
+        assert_eq!(
+            guess_precision(b"_izlhA~rlgdF_{geC~ywl@_kwzCn`{nI"),
+            Ok((6, 1.0))
+        );
+    }
+
+    #[test]
+    fn test_guess_precision_sparse() {
+        // around 200 metres apart, which is plausible at either precision
+        let input: Vec<[f64; 2]> = (0..20)
+            .map(|i| [13.4 + 0.002 * i as f64, 52.5 + 0.001 * i as f64])
+            .collect();
+        let encoded = codec::encode_fixture(&input, 5);
+        let (precision, confidence) = guess_precision(encoded.as_bytes()).unwrap();
+        assert_eq!(precision, 5);
+        assert!(confidence < 0.7, "{}", confidence);
+        // a handful of points over 100 metres apart, which would all be within 1° of 0°, 0° at precision 6
+        let input: Vec<[f64; 2]> = include!("../test_fixtures/lagos_sparse.rs");
+        let encoded = codec::encode_fixture(&input, 5);
+        let (precision, confidence) = guess_precision(encoded.as_bytes()).unwrap();
+        assert_eq!(precision, 5);
+        assert!(confidence > 0.5 && confidence < 0.9, "{}", confidence);
+        // a single point has no distances, so only its position counts
+        let encoded = codec::encode_fixture(&[[13.4, 52.5]], 5);
+        assert_eq!(guess_precision(encoded.as_bytes()), Ok((5, 2.0 / 3.0)));
+    }
+
+    #[test]
+    fn test_guess_precision_low_latitude() {
+        // close to 0°, 0°, where precision 5 is in range too, with points around 30 metres apart
+        let input: Vec<[f64; 2]> = include!("../test_fixtures/lagos.rs");
+        let encoded = codec::encode_fixture(&input, 6);
+        let (precision, confidence) = guess_precision(encoded.as_bytes()).unwrap();
+        assert_eq!(precision, 6);
+        assert!(confidence < 0.6, "{}", confidence);
+    }
+
+    #[test]
+    fn test_guess_precision_failures() {
+        assert_eq!(
+            guess_precision(b"_ibE_seK_se"),
+            Err(Error::Truncated { idx: 8 })
+        );
+        // a latitude of around -1.07e14 is out of range even at precision 9
+        let err = guess_precision(b"~~~~~~_ibE?").unwrap_err();
+        assert_eq!(err.status(), PolylineStatus::CoordinateOutOfRange);
+        let (mut precision, mut confidence) = (1, 1.0);
+        let res = unsafe { polyline_guess_precision(ptr::null(), &mut precision, &mut confidence) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        assert_eq!((precision, confidence), (0, 0.0));
+    }
+
+    #[test]
+    fn test_bbox() {
+        let encoded: &str = include!("../test_fixtures/berlin_decoded.rs");
+        let decoded: Vec<[f64; 2]> = codec::coords(encoded.as_bytes(), 5)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let fold = |f: fn(f64, f64) -> f64, axis: usize, init: f64| {
+            decoded.iter().map(|c| c[axis]).fold(init, f)
+        };
+        let expected = BoundingBox {
+            west: fold(f64::min, 0, f64::INFINITY),
+            south: fold(f64::min, 1, f64::INFINITY),
+            east: fold(f64::max, 0, f64::NEG_INFINITY),
+            north: fold(f64::max, 1, f64::NEG_INFINITY),
+        };
+        for antimeridian in [false, true] {
+            let mut out = BoundingBox::empty();
+            let res = unsafe {
+                polyline_bbox(encoded.as_ptr(), encoded.len(), 5, antimeridian, &mut out)
+            };
+            assert_eq!(res, PolylineResult::ok());
+            assert_eq!(out, expected);
+        }
+
+        // from Fiji to Samoa, across the antimeridian
+        let route = [[178.5, -18.1], [-179.9, -17.0], [-171.8, -13.8]];
+        let encoded = codec::encode_fixture(&route, 6);
+        let crossing = bbox(encoded.as_bytes(), 6, true).unwrap();
+        assert_eq!(
+            crossing,
+            BoundingBox {
+                west: 178.5,
+                south: -18.1,
+                east: -171.8,
+                north: -13.8
+            }
+        );
+        let wide = bbox(encoded.as_bytes(), 6, false).unwrap();
+        assert_eq!((wide.west, wide.east), (-179.9, 178.5));
+
+        let cases: [(&[f64], (f64, f64)); 5] = [
+            // the crossing box needn't span less than 180°
+            (&[-170.0, 10.0, 170.0], (10.0, -170.0)),
+            (&[-10.0, 10.0], (-10.0, 10.0)),
+            // ties are broken in favour of not crossing the antimeridian
+            (&[-90.0, 90.0], (-90.0, 90.0)),
+            // -180° and 180° are the same meridian
+            (&[-180.0, 180.0], (180.0, 180.0)),
+            // the largest gap is around neither 0° nor ±180°, so the box is wider than the narrowest one
+            (&[30.0, 150.0, -150.0, -30.0], (-150.0, 150.0)),
+        ];
+        for (lons, expected) in cases {
+            let route: Vec<[f64; 2]> = lons.iter().map(|&lon| [lon, 0.0]).collect();
+            let encoded = codec::encode_fixture(&route, 5);
+            let bbox = bbox(encoded.as_bytes(), 5, true).unwrap();
+            assert_eq!((bbox.west, bbox.east), expected, "{:?}", lons);
+        }
+    }
+
+    #[test]
+    fn test_bbox_failures() {
+        assert_eq!(
+            bbox(b"", 5, false).unwrap_err().status(),
+            PolylineStatus::InvalidArgument
+        );
+        let err = bbox(b"_c~uP_seK", 5, false).unwrap_err();
+        assert_eq!(err.status(), PolylineStatus::CoordinateOutOfRange);
+        let mut out = BoundingBox {
+            west: 1.0,
+            south: 1.0,
+            east: 1.0,
+            north: 1.0,
+        };
+        let res = unsafe { polyline_bbox(b"_ibE_s".as_ptr(), 6, 5, false, &mut out) };
+        assert_eq!(res.status, PolylineStatus::TruncatedInput);
+        assert_eq!(out, BoundingBox::empty());
+        let res = unsafe { polyline_bbox(ptr::null(), 1, 5, false, &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+    }
+}
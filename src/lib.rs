@@ -7,41 +7,118 @@
 //!
 //! ## A Note on Coordinate Order
 //! This crate uses `Coordinate` and `LineString` types from the `geo-types` crate, which encodes coordinates in `(x, y)` order. The Polyline algorithm and first-party documentation assumes the _opposite_ coordinate order. It is thus advisable to pay careful attention to the order of the coordinates you use for encoding and decoding.
+//! [`decode_polyline_ordered`](fn.decode_polyline_ordered.html) and [`encode_coordinates_ordered`](fn.encode_coordinates_ordered.html)
+//! accept a [`CoordinateOrder`](enum.CoordinateOrder.html), and will swap the coordinates as required.
+//!
+//! ## Errors and Panics
+//! Most functions return a [`PolylineResult`](struct.PolylineResult.html) describing any failure, and every function
+//! records a description of its most recent failure, which can be retrieved using
+//! [`polyline_last_error_message`](fn.polyline_last_error_message.html).
+//! No function in this crate will unwind into its caller: an internal panic is contained, and reported as a failure.
+//!
+//! Wherever a function takes a pointer along with a length or count, the pointer may be `NULL` if the length is `0`.
 
 #![deny(
     clippy::cast_slice_from_raw_parts,
     clippy::cast_slice_different_sizes,
-    clippy::invalid_null_ptr_usage,
+    invalid_null_arguments,
     clippy::ptr_as_ptr,
     clippy::transmute_ptr_to_ref
 )]
 
+use polyline::errors::PolylineError;
 use polyline::{decode_polyline, encode_coordinates};
 use std::ffi::{CStr, CString};
-use std::{f64, ptr};
-use std::{slice};
+use std::slice;
+use std::{f64, mem, ptr};
 
-use geo_types::{CoordFloat, LineString};
+mod batch;
+mod buffers;
+mod codec;
+mod error;
+mod inspect;
+mod measure;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod simplify;
+mod stream;
+mod transform;
+pub use batch::{decode_polylines_batch, drop_decoded_batch, DecodedBatch};
+pub use batch::{drop_encoded_batch, encode_coordinates_batch, EncodedBatch};
+pub use buffers::{decode_polyline_into, encode_coordinates_into, encoded_length_upper_bound};
+use error::{catch_panic, report, update_last_error, Error};
+pub use error::{polyline_last_error_length, polyline_last_error_message};
+pub use error::{CoordinateAxis, PolylineResult, PolylineStatus};
+pub use inspect::{
+    polyline_bbox, polyline_guess_precision, polyline_point_count, polyline_validate, BoundingBox,
+};
+pub use measure::{coordinates_length_m, polyline_length_m, LengthMethod};
+pub use measure::{drop_double_array, polyline_cumulative_distances, polyline_interpolate};
+pub use measure::{DoubleArray, InterpolationMode};
+#[cfg(feature = "parallel")]
+pub use parallel::{
+    decode_polylines_batch_parallel, encode_coordinates_batch_parallel, polyline_set_num_threads,
+};
+pub use simplify::{drop_index_array, polyline_simplify, simplify_coordinates};
+pub use simplify::{IndexArray, SimplifyAlgorithm};
+pub use stream::{decoder_feed, decoder_finish, decoder_free, decoder_new, decoder_next_points};
+pub use stream::{encoder_current, encoder_free, encoder_new, encoder_push, encoder_push_many};
+pub use stream::{PolylineDecoder, PolylineEncoder};
+pub use transform::{polyline_append, polyline_concat, polyline_transcode};
+
+use geo_types::{Coord, CoordFloat, LineString};
 use libc::c_char;
 
-// we only want to allow 5 or 6, but we need the previous values for the cast to work
-#[allow(dead_code)]
-enum Precision {
-    Zero,
-    One,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-}
+// The polyline crate computes its scale factor as an i32, so 10^9 is the largest it can represent.
+// At that precision the largest possible delta (a 360° longitude change) still leaves plenty of i64 headroom
+const MAX_PRECISION: u32 = 9;
+const _: () = assert!(10i32.checked_pow(MAX_PRECISION).is_some());
+const _: () = assert!(2 * 360 * 10i64.pow(MAX_PRECISION) < i64::MAX);
 
-// We currently only allow 5 or 6
+// Any precision from 0 to MAX_PRECISION is valid
 fn get_precision(input: u32) -> Option<u32> {
-    match input {
-        5 => Some(Precision::Five as u32),
-        6 => Some(Precision::Six as u32),
-        _ => None,
+    (input <= MAX_PRECISION).then_some(input)
+}
+
+/// The order of the values in each coordinate pair
+///
+/// `LonLat` matches the `(x, y)` order of `geo-types`, and is used by the functions which don't take an order.
+/// `LatLon` matches the order used by the Polyline algorithm and its first-party documentation.
+///
+/// Functions taking an order accept it as an unsigned 32-bit `int`, and reject unknown values.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateOrder {
+    LonLat = 0,
+    LatLon,
+}
+
+// Enums are passed across the FFI boundary as integers, so that unknown values can be rejected rather than causing UB.
+// The error is the unknown value
+impl TryFrom<u32> for CoordinateOrder {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CoordinateOrder::LonLat),
+            1 => Ok(CoordinateOrder::LatLon),
+            _ => Err(value),
+        }
+    }
+}
+
+// Convert an enum passed across the FFI boundary as an integer, reporting an unknown value as an invalid argument
+fn enum_arg<T: TryFrom<u32>>(value: u32, msg: &'static str) -> Result<T, Error> {
+    T::try_from(value).map_err(|_| Error::Argument(msg))
+}
+
+impl CoordinateOrder {
+    // Convert a pair between this order and LonLat. Swapping is its own inverse, so this works in either direction
+    fn apply(self, coord: [f64; 2]) -> [f64; 2] {
+        match self {
+            CoordinateOrder::LonLat => coord,
+            CoordinateOrder::LatLon => [coord[1], coord[0]],
+        }
     }
 }
 
@@ -61,6 +138,16 @@ pub struct InternalArray {
     pub len: libc::size_t,
 }
 
+impl InternalArray {
+    // An array which owns no data, and is safe to drop
+    fn empty() -> Self {
+        InternalArray {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+}
+
 impl Drop for InternalArray {
     fn drop(&mut self) {
         if self.data.is_null() {
@@ -94,6 +181,8 @@ where
 // Build a LineString from an InternalArray
 impl From<InternalArray> for LineString<f64> {
     fn from(arr: InternalArray) -> Self {
+        // we're taking ownership of the data, so it mustn't be freed again when arr goes out of scope
+        let arr = mem::ManuallyDrop::new(arr);
         // we originated this data, so pointer-to-slice -> box -> vec
         unsafe {
             let p = ptr::slice_from_raw_parts_mut(arr.data.cast::<[f64; 2]>(), arr.len);
@@ -116,6 +205,48 @@ impl From<Vec<[f64; 2]>> for InternalArray {
     }
 }
 
+/// A C-compatible `struct` originating **inside** Rust
+/// used for passing arrays of numbers across the FFI boundary: `data` points to `len` values
+#[repr(C)]
+pub struct OwnedSlice<T> {
+    pub data: *mut T,
+    pub len: libc::size_t,
+}
+
+impl<T> OwnedSlice<T> {
+    // A slice which owns no data, and is safe to drop
+    fn empty() -> Self {
+        OwnedSlice {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> Drop for OwnedSlice<T> {
+    fn drop(&mut self) {
+        if self.data.is_null() {
+            return;
+        }
+        unsafe {
+            // we originated this data, so pointer-to-slice -> box
+            let p = ptr::slice_from_raw_parts_mut(self.data, self.len);
+            drop(Box::from_raw(p));
+        };
+    }
+}
+
+// Build an OwnedSlice from a Vec, so it can be leaked across the FFI boundary
+impl<T> From<Vec<T>> for OwnedSlice<T> {
+    fn from(v: Vec<T>) -> Self {
+        let boxed = v.into_boxed_slice();
+        OwnedSlice {
+            len: boxed.len(),
+            data: Box::into_raw(boxed).cast::<T>(),
+        }
+    }
+}
+
 // Build an InternalArray from a LineString, so it can be leaked across the FFI boundary
 impl From<Vec<[f64; 2]>> for ExternalArray {
     fn from(v: Vec<[f64; 2]>) -> Self {
@@ -129,42 +260,156 @@ impl From<Vec<[f64; 2]>> for ExternalArray {
     }
 }
 
-// Build a LineString from an ExternalArray
-impl From<ExternalArray> for LineString<f64> {
-    fn from(arr: ExternalArray) -> Self {
-        // we need to take ownership of this data, so slice -> vec
-        unsafe {
-            let v = slice::from_raw_parts(arr.data as *mut [f64; 2], arr.len).to_vec();
-            v.into()
+// Check that len elements of T can be covered by a single slice, which may not span more than isize::MAX bytes
+fn check_len<T>(len: usize) -> Result<(), Error> {
+    let addressable = len
+        .checked_mul(mem::size_of::<T>())
+        .is_some_and(|bytes| bytes <= isize::MAX as usize);
+    if addressable {
+        Ok(())
+    } else {
+        Err(Error::Length { len })
+    }
+}
+
+// Borrow an input array of count elements. NULL is allowed if count is 0
+unsafe fn input_slice<'a, T>(data: *const T, count: usize) -> Result<&'a [T], Error> {
+    if count == 0 {
+        return Ok(&[]);
+    }
+    if data.is_null() {
+        return Err(Error::NullPointer);
+    }
+    check_len::<T>(count)?;
+    Ok(slice::from_raw_parts(data, count))
+}
+
+impl ExternalArray {
+    // Borrow the coordinates, checking that the pointer and length can form a valid slice. NULL is allowed if len is 0
+    unsafe fn as_slice(&self) -> Result<&[[f64; 2]], Error> {
+        input_slice(self.data.cast::<[f64; 2]>(), self.len)
+    }
+}
+
+// Borrow a NUL-terminated C string as a str, reporting the offset of any invalid UTF-8
+unsafe fn str_from_ptr<'a>(pl: *const c_char) -> Result<&'a str, Error> {
+    if pl.is_null() {
+        return Err(Error::NullPointer);
+    }
+    CStr::from_ptr(pl).to_str().map_err(|e| Error::Utf8 {
+        idx: e.valid_up_to(),
+    })
+}
+
+// Borrow length-delimited bytes as a str, reporting the offset of any invalid UTF-8. NULL is allowed if len is 0
+unsafe fn str_from_raw_parts<'a>(data: *const u8, len: usize) -> Result<&'a str, Error> {
+    std::str::from_utf8(input_slice(data, len)?).map_err(|e| Error::Utf8 {
+        idx: e.valid_up_to(),
+    })
+}
+
+// Write the outcome of a decoding call to out, which must not be NULL.
+// out always receives an array, which is empty on failure
+unsafe fn write_array(
+    out: *mut InternalArray,
+    result: Result<InternalArray, Error>,
+) -> PolylineResult {
+    match result {
+        Ok(arr) => {
+            out.write(arr);
+            report(Ok(()))
+        }
+        Err(e) => {
+            out.write(InternalArray::empty());
+            report(Err(e))
         }
     }
 }
 
-// Decode a Polyline into an InternalArray
-fn arr_from_string(incoming: &str, precision: u32) -> InternalArray {
-    let result: InternalArray = if get_precision(precision).is_some() {
-        match decode_polyline(incoming, precision) {
-            Ok(res) => res.into(),
-            Err(_) => vec![[f64::NAN, f64::NAN]].into(),
+// Write the outcome of an encoding call to out, which must not be NULL.
+// out receives NULL on failure
+unsafe fn write_string(out: *mut *mut c_char, result: Result<String, Error>) -> PolylineResult {
+    let result = result.and_then(|s| {
+        CString::new(s).map_err(|_| Error::Polyline(PolylineError::EncodeToCharError))
+    });
+    match result {
+        Ok(s) => {
+            out.write(s.into_raw());
+            report(Ok(()))
         }
-    } else {
-        // bad precision parameter
-        vec![[f64::NAN, f64::NAN]].into()
-    };
-    result
+        Err(e) => {
+            out.write(ptr::null_mut());
+            report(Err(e))
+        }
+    }
 }
 
-// Decode an Array into a Polyline
+// Decode a Polyline into a LineString exactly as the polyline crate does, accepting an unterminated final value
+fn try_ls_from_string(incoming: &str, precision: u32) -> Result<LineString<f64>, Error> {
+    #[cfg(test)]
+    error::injected_panic();
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    Ok(decode_polyline(incoming, precision)?)
+}
+
+// Decode a Polyline into coordinate pairs, reporting the reason for any failure.
+// This uses the same decoder as every other checked entry point, so they all report the same failure
+fn try_vec_from_string(
+    incoming: &str,
+    precision: u32,
+    order: CoordinateOrder,
+) -> Result<Vec<[f64; 2]>, Error> {
+    #[cfg(test)]
+    error::injected_panic();
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    codec::coords(incoming.as_bytes(), precision)
+        .map(|coord| coord.map(|coord| order.apply(coord)))
+        .collect()
+}
+
+// Decode a Polyline into an InternalArray, reporting the reason for any failure
+fn try_arr_from_string(
+    incoming: &str,
+    precision: u32,
+    order: CoordinateOrder,
+) -> Result<InternalArray, Error> {
+    try_vec_from_string(incoming, precision, order).map(Into::into)
+}
+
+// Decode a Polyline into an InternalArray.
+// This doesn't check that the final value is terminated, so that decode_polyline_ffi behaves as it always has
+fn arr_from_string(incoming: &str, precision: u32) -> InternalArray {
+    update_last_error(catch_panic(|| {
+        try_ls_from_string(incoming, precision).map(Into::into)
+    }))
+    .unwrap_or_else(|_| vec![[f64::NAN, f64::NAN]].into())
+}
+
+// Encode an Array into a Polyline, reporting the reason for any failure
+fn try_string_from_arr(
+    incoming: ExternalArray,
+    precision: u32,
+    order: CoordinateOrder,
+) -> Result<String, Error> {
+    #[cfg(test)]
+    error::injected_panic();
+    let inc = unsafe { incoming.as_slice()? };
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    let coords = inc.iter().map(|&coord| {
+        let [x, y] = order.apply(coord);
+        Coord { x, y }
+    });
+    Ok(encode_coordinates(coords, precision)?)
+}
+
+// Encode an Array into a Polyline
 fn string_from_arr(incoming: ExternalArray, precision: u32) -> String {
-    let inc: LineString<_> = incoming.into();
-    if get_precision(precision).is_some() {
-        match encode_coordinates(Into::<LineString<_>>::into(inc), precision) {
-            Ok(res) => res,
-            // we don't need to adapt the error
-            Err(res) => res.to_string(),
-        }
-    } else {
-        "Bad precision parameter supplied".to_string()
+    let result = catch_panic(|| try_string_from_arr(incoming, precision, CoordinateOrder::LonLat));
+    match update_last_error(result) {
+        Ok(res) => res,
+        Err(Error::Precision(_)) => "Bad precision parameter supplied".to_string(),
+        // we don't need to adapt the error
+        Err(res) => res.to_string(),
     }
 }
 
@@ -174,9 +419,10 @@ fn string_from_arr(incoming: ExternalArray, precision: u32) -> String {
 ///
 /// - a pointer to `NUL`-terminated characters (`char*`)
 /// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
-/// OSRM and Valhalla Polylines)
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
 ///
 /// A decoding failure will return an [Array](struct.Array.html) whose `data` field is `[[NaN, NaN]]`, and whose `len` field is `1`.
+/// The reason for the failure can be retrieved using [`polyline_last_error_message`](fn.polyline_last_error_message.html).
 ///
 /// Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
 /// with the returned [Array](struct.Array.html), in order to free the memory it allocates.
@@ -186,12 +432,82 @@ fn string_from_arr(incoming: ExternalArray, precision: u32) -> String {
 /// This function is unsafe because it accesses a raw pointer which could contain arbitrary data
 #[no_mangle]
 pub unsafe extern "C" fn decode_polyline_ffi(pl: *const c_char, precision: u32) -> InternalArray {
-    let s = CStr::from_ptr(pl).to_str();
-    if let Ok(unwrapped) = s {
-        arr_from_string(unwrapped, precision)
-    } else {
-        vec![[f64::NAN, f64::NAN]].into()
+    match update_last_error(str_from_ptr(pl)) {
+        Ok(unwrapped) => arr_from_string(unwrapped, precision),
+        Err(_) => vec![[f64::NAN, f64::NAN]].into(),
+    }
+}
+
+/// Convert a Polyline into an array of coordinates, reporting the reason for any failure
+///
+/// Callers must pass three arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a pointer to an [InternalArray](struct.InternalArray.html), which will receive the decoded coordinates
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html). If its `status` is anything other than `Ok`,
+/// `out` will hold an empty array, and `position` will hold the byte offset of the failure where one is known.
+/// Unlike [`decode_polyline_ffi`](fn.decode_polyline_ffi.html), a final value with no terminating chunk is reported
+/// as `TruncatedInput`.
+///
+/// Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
+/// with the array written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decode_polyline_ffi_checked(
+    pl: *const c_char,
+    precision: u32,
+    out: *mut InternalArray,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
     }
+    let result = catch_panic(|| {
+        str_from_ptr(pl).and_then(|s| try_arr_from_string(s, precision, CoordinateOrder::LonLat))
+    });
+    write_array(out, result)
+}
+
+/// Convert a length-delimited Polyline into an array of coordinates, reporting the reason for any failure
+///
+/// Callers must pass four arguments:
+///
+/// - a pointer to the Polyline's bytes (`uint8_t*`), which need not be `NUL`-terminated
+/// - the number of bytes, as a `size_t`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a pointer to an [InternalArray](struct.InternalArray.html), which will receive the decoded coordinates
+///
+/// The bytes must be valid UTF-8. This allows Polylines to be decoded in place from a larger buffer.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+///
+/// Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
+/// with the array written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decode_polyline_bytes(
+    data: *const u8,
+    len: libc::size_t,
+    precision: u32,
+    out: *mut InternalArray,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        str_from_raw_parts(data, len)
+            .and_then(|s| try_arr_from_string(s, precision, CoordinateOrder::LonLat))
+    });
+    write_array(out, result)
 }
 
 /// Convert an array of coordinates into a Polyline
@@ -202,13 +518,16 @@ pub unsafe extern "C" fn decode_polyline_ffi(pl: *const c_char, precision: u32)
 ///     - `data`, a void pointer to an array of floating-point lat, lon coordinates: `[[1.0, 2.0]]`
 ///     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
 /// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
-/// OSRM and Valhalla Polylines)
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
 ///
 /// A decoding failure will return one of the following:
 ///
 /// - a `char*` beginning with "Longitude error:" if invalid longitudes are passed
 /// - a `char*` beginning with "Latitude error:" if invalid latitudes are passed
 ///
+/// As the error is returned in-band, callers should check [`polyline_last_error_length`](fn.polyline_last_error_length.html),
+/// which is `0` if the call succeeded.
+///
 /// Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
 /// with the returned `c_char` pointer, in order to free the memory it allocates.
 ///
@@ -221,29 +540,157 @@ pub extern "C" fn encode_coordinates_ffi(coords: ExternalArray, precision: u32)
     match CString::new(s) {
         Ok(res) => res.into_raw(),
         // It's arguably better to fail noisily, but this is robust
-        Err(res) => {
-            let err = CString::new(res.to_string()).unwrap();
-            err.into_raw()
-        }
+        Err(res) => CString::new(res.to_string()).unwrap_or_default().into_raw(),
     }
 }
 
+/// Convert an array of coordinates into a Polyline, reporting the reason for any failure
+///
+/// Callers must pass three arguments:
+///
+/// - a [Struct](struct.ExternalArray.html) with two fields:
+///     - `data`, a void pointer to an array of floating-point lon, lat coordinates: `[[2.0, 1.0]]`
+///     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a pointer to a `char*`, which will receive the encoded Polyline
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html). If its `status` is anything other than `Ok`,
+/// `out` will be set to `NULL`. If a coordinate is out of range, `position` will hold its index in
+/// the array, and `axis` will identify whether its latitude or longitude was invalid.
+///
+/// Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+/// with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encode_coordinates_ffi_checked(
+    coords: ExternalArray,
+    precision: u32,
+    out: *mut *mut c_char,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| try_string_from_arr(coords, precision, CoordinateOrder::LonLat));
+    write_string(out, result)
+}
+
+/// Convert a Polyline into an array of coordinates in the requested order, reporting the reason for any failure
+///
+/// Callers must pass four arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a [CoordinateOrder](enum.CoordinateOrder.html) value as an unsigned 32-bit `int`, specifying the order of each
+///   coordinate pair in the output
+/// - a pointer to an [InternalArray](struct.InternalArray.html), which will receive the decoded coordinates
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// The status is `InvalidArgument` if `order` isn't a `CoordinateOrder` value.
+///
+/// Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
+/// with the array written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decode_polyline_ordered(
+    pl: *const c_char,
+    precision: u32,
+    order: u32,
+    out: *mut InternalArray,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let order = enum_arg(order, "unknown coordinate order")?;
+        try_arr_from_string(str_from_ptr(pl)?, precision, order)
+    });
+    write_array(out, result)
+}
+
+/// Convert an array of coordinates in the given order into a Polyline, reporting the reason for any failure
+///
+/// Callers must pass four arguments:
+///
+/// - a [Struct](struct.ExternalArray.html) with two fields:
+///     - `data`, a void pointer to an array of floating-point coordinate pairs, in the order given by `order`
+///     - `len`, the length of the array being passed. Its type must be `size_t`
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a [CoordinateOrder](enum.CoordinateOrder.html) value as an unsigned 32-bit `int`, specifying the order of each
+///   coordinate pair in `coords`
+/// - a pointer to a `char*`, which will receive the encoded Polyline
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`encode_coordinates_ffi_checked`](fn.encode_coordinates_ffi_checked.html).
+/// The status is `InvalidArgument` if `order` isn't a `CoordinateOrder` value.
+///
+/// Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+/// with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encode_coordinates_ordered(
+    coords: ExternalArray,
+    precision: u32,
+    order: u32,
+    out: *mut *mut c_char,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let order = enum_arg(order, "unknown coordinate order")?;
+        try_string_from_arr(coords, precision, order)
+    });
+    write_string(out, result)
+}
+
+/// Return the largest precision value accepted by this library
+///
+/// Every function taking a precision argument accepts values from `0` up to and including this value.
+#[no_mangle]
+pub extern "C" fn polyline_max_precision() -> u32 {
+    MAX_PRECISION
+}
+
 /// Free Array memory which Rust has allocated across the FFI boundary
 ///
 /// # Safety
 ///
 /// This function is unsafe because it accesses a raw pointer which could contain arbitrary data
 #[no_mangle]
-pub extern "C" fn drop_float_array(_: InternalArray) {}
+pub extern "C" fn drop_float_array(arr: InternalArray) {
+    let _ = catch_panic(|| {
+        drop(arr);
+        Ok(())
+    });
+}
 
 /// Free `CString` memory which Rust has allocated across the FFI boundary
 ///
+/// Passing `NULL` has no effect.
+///
 /// # Safety
 ///
 /// This function is unsafe because it accesses a raw pointer which could contain arbitrary data
 #[no_mangle]
 pub unsafe extern "C" fn drop_cstring(p: *mut c_char) {
-    drop(CString::from_raw(p));
+    if p.is_null() {
+        return;
+    }
+    let _ = catch_panic(|| {
+        drop(CString::from_raw(p));
+        Ok(())
+    });
 }
 
 #[cfg(test)]
@@ -278,12 +725,21 @@ mod tests {
         let transformed: InternalArray = super::arr_from_string(input, 5);
         // Array to LS via slice, as we want to take ownership of a copy for testing purposes
         let v = unsafe {
-            slice::from_raw_parts(transformed.data as *mut [f64; 2], transformed.len).to_vec()
+            slice::from_raw_parts(transformed.data.cast::<[f64; 2]>(), transformed.len).to_vec()
         };
         let ls: LineString<_> = v.into();
         assert_eq!(ls, output.into());
     }
 
+    #[test]
+    fn test_unterminated_string_conversion() {
+        // the unchecked entry point decodes an unterminated final value, as it always has
+        let transformed: LineString<_> = super::arr_from_string("_ibE_seK_seK_se", 5).into();
+        assert_eq!(transformed.0.len(), 2);
+        assert_eq!(transformed.0[0], Coord { x: 2.0, y: 1.0 });
+        assert_eq!(polyline_last_error_length(), 0);
+    }
+
     #[test]
     #[should_panic]
     fn test_bad_string_conversion() {
@@ -293,12 +749,373 @@ mod tests {
         let transformed: InternalArray = super::arr_from_string(input, 5);
         // Array to LS via slice, as we want to take ownership of a copy for testing purposes
         let v = unsafe {
-            slice::from_raw_parts(transformed.data as *mut [f64; 2], transformed.len).to_vec()
+            slice::from_raw_parts(transformed.data.cast::<[f64; 2]>(), transformed.len).to_vec()
         };
         let ls: LineString<_> = v.into();
         assert_eq!(ls, output.into());
     }
 
+    #[test]
+    fn test_checked_decode() {
+        let input = CString::new("_ibE_seK_seK_seK").unwrap();
+        let mut out = InternalArray::empty();
+        let res = unsafe { decode_polyline_ffi_checked(input.as_ptr(), 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        assert_eq!(out.len, 2);
+        drop_float_array(out);
+    }
+
+    #[test]
+    fn test_checked_decode_failures() {
+        let cases = [
+            ("_ibE_seK_seK_seK", 10, PolylineStatus::InvalidPrecision, 0),
+            ("_ibE_seK_seK_se", 5, PolylineStatus::TruncatedInput, 12),
+            ("_ibE_seK_seK", 5, PolylineStatus::TruncatedInput, 8),
+            ("_ibE_seK_seK_", 5, PolylineStatus::TruncatedInput, 12),
+            ("_ib E_seK", 5, PolylineStatus::InvalidCharacter, 3),
+            (
+                "_ibE_seK_seK_seK_ibE",
+                5,
+                PolylineStatus::TruncatedInput,
+                16,
+            ),
+        ];
+        for (input, precision, status, position) in cases {
+            let input = CString::new(input).unwrap();
+            let mut out = InternalArray::empty();
+            let res = unsafe { decode_polyline_ffi_checked(input.as_ptr(), precision, &mut out) };
+            assert_eq!(res.status, status, "{:?}", input);
+            assert_eq!(res.position, position, "{:?}", input);
+            drop_float_array(out);
+        }
+    }
+
+    #[test]
+    fn test_checked_decode_bad_utf8() {
+        let input = CString::new(vec![b'_', b'i', 0xff, b'E']).unwrap();
+        let mut out = InternalArray::empty();
+        let res = unsafe { decode_polyline_ffi_checked(input.as_ptr(), 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::InvalidUtf8);
+        assert_eq!(res.position, 2);
+        assert!(out.data.is_null());
+    }
+
+    #[test]
+    fn test_last_error_from_entry_points() {
+        let input = vec![[2.0, 1.0], [4.0, 3.0]];
+        let _ = super::string_from_arr(input.clone().into(), 5);
+        assert_eq!(polyline_last_error_length(), 0);
+        let transformed = super::string_from_arr(input.into(), 12);
+        assert_eq!(transformed, "Bad precision parameter supplied");
+        assert!(polyline_last_error_length() > 0);
+        drop_float_array(super::arr_from_string("_ibE_seK_seK_seK", 5));
+        assert!(polyline_last_error_message().is_null());
+        drop_float_array(super::arr_from_string("_ib E_seK", 5));
+        let msg = unsafe { CStr::from_ptr(polyline_last_error_message()) };
+        assert_eq!(msg.to_str().unwrap(), "cannot decode character at index 3");
+    }
+
+    #[test]
+    fn test_checked_encode() {
+        let input: ExternalArray = vec![[2.0, 1.0], [4.0, 3.0]].into();
+        let mut out = ptr::null_mut();
+        let res = unsafe { encode_coordinates_ffi_checked(input, 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        let encoded = unsafe { CStr::from_ptr(out) };
+        assert_eq!(encoded.to_str().unwrap(), "_ibE_seK_seK_seK");
+        unsafe { drop_cstring(out) };
+    }
+
+    #[test]
+    fn test_checked_encode_out_of_range() {
+        let mut out = ptr::null_mut();
+        let input: ExternalArray = vec![[2.0, 1.0], [4.0, 3.0], [4.0, 91.0]].into();
+        let res = unsafe { encode_coordinates_ffi_checked(input, 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(res.position, 2);
+        assert_eq!(res.axis, CoordinateAxis::Latitude);
+        assert!(out.is_null());
+
+        let input: ExternalArray = vec![[2.0, 1.0], [-180.5, 3.0]].into();
+        let res = unsafe { encode_coordinates_ffi_checked(input, 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(res.position, 1);
+        assert_eq!(res.axis, CoordinateAxis::Longitude);
+        assert!(out.is_null());
+    }
+
+    #[test]
+    fn test_precision_range() {
+        assert_eq!(polyline_max_precision(), 9);
+        let input = vec![[13.3888549, 52.5170365], [-120.95, 40.7]];
+        for precision in 0..=polyline_max_precision() {
+            let encoded = super::string_from_arr(input.clone().into(), precision);
+            assert_eq!(polyline_last_error_length(), 0);
+            let decoded: LineString<_> =
+                super::try_arr_from_string(&encoded, precision, CoordinateOrder::LonLat)
+                    .unwrap()
+                    .into();
+            let tolerance = 0.5 / 10f64.powi(precision as i32);
+            for (orig, dec) in input.iter().zip(decoded.0) {
+                assert!((orig[0] - dec.x).abs() <= tolerance);
+                assert!((orig[1] - dec.y).abs() <= tolerance);
+            }
+        }
+        assert_eq!(
+            super::string_from_arr(input.into(), 10),
+            "Bad precision parameter supplied"
+        );
+    }
+
+    #[test]
+    fn test_null_guards() {
+        let mut out = InternalArray::empty();
+        let res = unsafe { decode_polyline_ffi_checked(ptr::null(), 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        let input = CString::new("_ibE_seK").unwrap();
+        let res = unsafe { decode_polyline_ffi_checked(input.as_ptr(), 5, ptr::null_mut()) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        let arr = unsafe { decode_polyline_ffi(ptr::null(), 5) };
+        assert_eq!(arr.len, 1);
+
+        let mut encoded = ptr::null_mut();
+        let input = ExternalArray {
+            data: ptr::null(),
+            len: 3,
+        };
+        let res = unsafe { encode_coordinates_ffi_checked(input, 5, &mut encoded) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        assert!(encoded.is_null());
+        // NULL is allowed for an empty input
+        let input = ExternalArray {
+            data: ptr::null(),
+            len: 0,
+        };
+        let res = unsafe { encode_coordinates_ffi_checked(input, 5, &mut encoded) };
+        assert_eq!(res, PolylineResult::ok());
+        assert_eq!(unsafe { CStr::from_ptr(encoded) }.to_bytes(), b"");
+        unsafe { drop_cstring(encoded) };
+        let res = unsafe { decode_polyline_bytes(ptr::null(), 0, 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        assert_eq!(out.len, 0);
+        let input: ExternalArray = vec![[2.0, 1.0]].into();
+        let res = unsafe { encode_coordinates_ffi_checked(input, 5, ptr::null_mut()) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        unsafe { drop_cstring(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn test_length_guard() {
+        let coords = [[2.0, 1.0]];
+        let mut encoded = ptr::null_mut();
+        let input = ExternalArray {
+            data: coords.as_ptr().cast(),
+            len: usize::MAX / 8,
+        };
+        let res = unsafe { encode_coordinates_ffi_checked(input, 5, &mut encoded) };
+        assert_eq!(res.status, PolylineStatus::InvalidLength);
+        assert!(encoded.is_null());
+    }
+
+    #[test]
+    fn test_panic_does_not_unwind() {
+        error::INJECT_PANIC.with(|inject| inject.set(true));
+        let input = CString::new("_ibE_seK_seK_seK").unwrap();
+        let mut out = InternalArray::empty();
+        let res = unsafe { decode_polyline_ffi_checked(input.as_ptr(), 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::Panic);
+        assert!(out.data.is_null());
+        let msg = unsafe { CStr::from_ptr(polyline_last_error_message()) };
+        assert_eq!(msg.to_str().unwrap(), "internal panic: injected panic");
+
+        let arr = unsafe { decode_polyline_ffi(input.as_ptr(), 5) };
+        assert_eq!(arr.len, 1);
+        assert!(polyline_last_error_length() > 0);
+        drop_float_array(arr);
+
+        let encoded = encode_coordinates_ffi(vec![[2.0, 1.0]].into(), 5);
+        let msg = unsafe { CStr::from_ptr(encoded) };
+        assert_eq!(msg.to_str().unwrap(), "internal panic: injected panic");
+        unsafe { drop_cstring(encoded) };
+        error::INJECT_PANIC.with(|inject| inject.set(false));
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        // a Polyline embedded in a larger buffer, with no terminator
+        let buf = b"{\"route\":\"_ibE_seK_seK_seK\"}";
+        let mut out = InternalArray::empty();
+        let res = unsafe { decode_polyline_bytes(buf[10..].as_ptr(), 16, 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        let ls: LineString<_> = out.into();
+        assert_eq!(ls, vec![[2.0, 1.0], [4.0, 3.0]].into());
+
+        let mut out = InternalArray::empty();
+        let buf = [b'_', b'i', 0xff, b'E'];
+        let res = unsafe { decode_polyline_bytes(buf.as_ptr(), buf.len(), 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::InvalidUtf8);
+        assert_eq!(res.position, 2);
+        let res = unsafe { decode_polyline_bytes(ptr::null(), 4, 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        let res = unsafe { decode_polyline_bytes(buf.as_ptr(), usize::MAX, 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::InvalidLength);
+    }
+
+    #[test]
+    fn test_coordinate_order() {
+        let input = CString::new("_ibE_seK_seK_seK").unwrap();
+        let mut out = InternalArray::empty();
+        let res = unsafe {
+            decode_polyline_ordered(input.as_ptr(), 5, CoordinateOrder::LatLon as u32, &mut out)
+        };
+        assert_eq!(res, PolylineResult::ok());
+        let ls: LineString<_> = out.into();
+        assert_eq!(ls, vec![[1.0, 2.0], [3.0, 4.0]].into());
+
+        let mut encoded = ptr::null_mut();
+        let coords: ExternalArray = vec![[1.0, 2.0], [3.0, 4.0]].into();
+        let res = unsafe {
+            encode_coordinates_ordered(coords, 5, CoordinateOrder::LatLon as u32, &mut encoded)
+        };
+        assert_eq!(res, PolylineResult::ok());
+        let s = unsafe { CStr::from_ptr(encoded) };
+        assert_eq!(s.to_str().unwrap(), "_ibE_seK_seK_seK");
+        unsafe { drop_cstring(encoded) };
+
+        // a latitude of 91 is only invalid in LatLon order
+        let coords: ExternalArray = vec![[91.0, 2.0]].into();
+        let res = unsafe {
+            encode_coordinates_ordered(coords, 5, CoordinateOrder::LatLon as u32, &mut encoded)
+        };
+        assert_eq!(res.status, PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(res.axis, CoordinateAxis::Latitude);
+
+        // unknown orders are rejected
+        let mut out = InternalArray::empty();
+        let res = unsafe { decode_polyline_ordered(input.as_ptr(), 5, 2, &mut out) };
+        assert_eq!(res.status, PolylineStatus::InvalidArgument);
+        assert_eq!(out.len, 0);
+        let coords: ExternalArray = vec![[1.0, 2.0]].into();
+        let res = unsafe { encode_coordinates_ordered(coords, 5, u32::MAX, &mut encoded) };
+        assert_eq!(res.status, PolylineStatus::InvalidArgument);
+        assert!(encoded.is_null());
+    }
+
+    // Run a Polyline through every checked decoder, freeing any output
+    fn decode_everywhere(pl: &CStr) -> Vec<(&'static str, PolylineResult)> {
+        let bytes = pl.to_bytes();
+        let mut results = vec![];
+        let mut arr = InternalArray::empty();
+        let res = unsafe { decode_polyline_ffi_checked(pl.as_ptr(), 5, &mut arr) };
+        drop_float_array(arr);
+        results.push(("decode_polyline_ffi_checked", res));
+        let mut arr = InternalArray::empty();
+        let res = unsafe { decode_polyline_ordered(pl.as_ptr(), 5, 0, &mut arr) };
+        drop_float_array(arr);
+        results.push(("decode_polyline_ordered", res));
+        let mut arr = InternalArray::empty();
+        let res = unsafe { decode_polyline_bytes(bytes.as_ptr(), bytes.len(), 5, &mut arr) };
+        drop_float_array(arr);
+        results.push(("decode_polyline_bytes", res));
+        let mut batch = DecodedBatch {
+            coords: InternalArray::empty(),
+            offsets: ptr::null_mut(),
+            statuses: ptr::null_mut(),
+            count: 0,
+        };
+        let res =
+            unsafe { decode_polylines_batch(&bytes.as_ptr(), &bytes.len(), 1, 5, &mut batch) };
+        assert_eq!(res, PolylineResult::ok());
+        results.push(("decode_polylines_batch", unsafe { *batch.statuses }));
+        drop_decoded_batch(batch);
+        let mut buf = [0.0; 64];
+        let mut written = 0;
+        let res =
+            unsafe { decode_polyline_into(pl.as_ptr(), 5, buf.as_mut_ptr(), 32, &mut written) };
+        results.push(("decode_polyline_into", res));
+        let decoder = decoder_new(5);
+        let mut res = unsafe { decoder_feed(decoder, bytes.as_ptr(), bytes.len()) };
+        if res == PolylineResult::ok() {
+            res = unsafe { decoder_finish(decoder) };
+        }
+        unsafe { decoder_free(decoder) };
+        results.push(("decoder_feed", res));
+        let res = unsafe { polyline_validate(bytes.as_ptr(), bytes.len(), 5, true) };
+        results.push(("polyline_validate", res));
+        let mut bbox = BoundingBox {
+            west: 0.0,
+            south: 0.0,
+            east: 0.0,
+            north: 0.0,
+        };
+        let res = unsafe { polyline_bbox(bytes.as_ptr(), bytes.len(), 5, false, &mut bbox) };
+        results.push(("polyline_bbox", res));
+        let mut length = 0.0;
+        let res = unsafe { polyline_length_m(pl.as_ptr(), 5, 0, &mut length) };
+        results.push(("polyline_length_m", res));
+        let mut distances = DoubleArray::from(vec![]);
+        let res = unsafe { polyline_cumulative_distances(pl.as_ptr(), 5, &mut distances) };
+        drop_double_array(distances);
+        results.push(("polyline_cumulative_distances", res));
+        let (mut coord, mut segment) = ([0.0; 2], 0);
+        let res = unsafe {
+            polyline_interpolate(pl.as_ptr(), 5, 0.0, 0, coord.as_mut_ptr(), &mut segment)
+        };
+        results.push(("polyline_interpolate", res));
+        let mut out = ptr::null_mut();
+        let res = unsafe { polyline_simplify(pl.as_ptr(), 5, 0.0, 0, &mut out, ptr::null_mut()) };
+        unsafe { drop_cstring(out) };
+        results.push(("polyline_simplify", res));
+        let mut out = ptr::null_mut();
+        let res = unsafe { polyline_transcode(pl.as_ptr(), 5, 6, &mut out) };
+        unsafe { drop_cstring(out) };
+        results.push(("polyline_transcode", res));
+        let mut out = ptr::null_mut();
+        let res = unsafe { polyline_append(pl.as_ptr(), 5, vec![].into(), &mut out) };
+        unsafe { drop_cstring(out) };
+        results.push(("polyline_append", res));
+        results
+    }
+
+    #[test]
+    fn test_checked_decoders_agree() {
+        let mut lon_out_of_range = vec![];
+        codec::encode_value(0, &mut |b| lon_out_of_range.push(b));
+        codec::encode_value(18_100_000, &mut |b| lon_out_of_range.push(b));
+        let corpus: Vec<Vec<u8>> = vec![
+            b"_p~iF~ps|U_ulLnnqC_mqNvxq`@".to_vec(),
+            // a latitude of 91, with and without a longitude
+            b"_c~uP".to_vec(),
+            b"_c~uP_seK".to_vec(),
+            b"_c~uP ".to_vec(),
+            lon_out_of_range,
+            // a second point which is out of range
+            b"_ibE_seK_c~uP_seK".to_vec(),
+            b"_ibE".to_vec(),
+            b"_ibE_s".to_vec(),
+            b"_ibE_seK_se".to_vec(),
+            b"_ibE_seK_seK".to_vec(),
+            b"_ibE_seK ".to_vec(),
+            b"~~~~~~~~~~~~~?".to_vec(),
+        ];
+        for input in corpus {
+            let input = CString::new(input).unwrap();
+            let results = decode_everywhere(&input);
+            let (_, expected) = results[0];
+            for (name, res) in results {
+                assert_eq!(res, expected, "{} disagrees on {:?}", name, input);
+            }
+        }
+        // the latitude is checked before the missing longitude is reported, as in the polyline crate
+        let input = CString::new("_c~uP").unwrap();
+        let res = decode_everywhere(&input)[0].1;
+        assert_eq!(res.status, PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(res.axis, CoordinateAxis::Latitude);
+        assert_eq!(res.position, 0);
+        assert!(decode_polyline("_c~uP", 5)
+            .is_err_and(|e| matches!(e, PolylineError::LatitudeCoordError { idx: 0, .. })));
+    }
+
     #[test]
     fn test_long_vec() {
         use std::clone::Clone;
@@ -0,0 +1,530 @@
+//! Entry points which measure distances along Polylines and coordinate arrays, using the `geo` crate
+
+use crate::codec::{self, check_coord};
+use crate::error::{catch_panic, report, Error, PolylineResult};
+use crate::{
+    enum_arg, get_precision, str_from_ptr, try_vec_from_string, CoordinateOrder, ExternalArray,
+    OwnedSlice,
+};
+use geo::{Distance, Geodesic, Haversine, InterpolatePoint, VincentyDistance};
+use geo_types::Point;
+use libc::c_char;
+use std::iter;
+
+/// The method used to measure distances between coordinates
+///
+/// - `Haversine` treats the Earth as a sphere. It is the fastest, and is accurate to within about 0.5%
+/// - `Vincenty` uses Vincenty's formulae on the WGS84 ellipsoid. It can fail to converge for nearly antipodal points
+/// - `Geodesic` uses Karney's geodesic algorithm on the WGS84 ellipsoid. It is the most accurate, and always converges
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthMethod {
+    Haversine = 0,
+    Vincenty,
+    Geodesic,
+}
+
+impl TryFrom<u32> for LengthMethod {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(LengthMethod::Haversine),
+            1 => Ok(LengthMethod::Vincenty),
+            2 => Ok(LengthMethod::Geodesic),
+            _ => Err(value),
+        }
+    }
+}
+
+/// How [`polyline_interpolate`](fn.polyline_interpolate.html) measures the distance along a Polyline
+///
+/// - `Fraction` is a fraction of the Polyline's length, from `0.0` to `1.0`
+/// - `Metres` is a distance in metres, from `0.0` to the Polyline's length
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationMode {
+    Fraction = 0,
+    Metres,
+}
+
+impl TryFrom<u32> for InterpolationMode {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(InterpolationMode::Fraction),
+            1 => Ok(InterpolationMode::Metres),
+            _ => Err(value),
+        }
+    }
+}
+
+/// A C-compatible `struct` originating **inside** Rust, holding `double` values
+///
+/// `data` points to `len` `double` values.
+pub type DoubleArray = OwnedSlice<f64>;
+
+// The distance in metres between two [lon, lat] coordinates. idx is the index of the first, for reporting failures
+fn distance(from: [f64; 2], to: [f64; 2], method: LengthMethod, idx: usize) -> Result<f64, Error> {
+    let (from, to) = (Point::from(from), Point::from(to));
+    match method {
+        LengthMethod::Haversine => Ok(Haversine.distance(from, to)),
+        LengthMethod::Vincenty => from
+            .vincenty_distance(&to)
+            .map_err(|_| Error::Convergence { idx }),
+        LengthMethod::Geodesic => Ok(Geodesic.distance(from, to)),
+    }
+}
+
+// The total length in metres of a sequence of [lon, lat] coordinates
+fn length(
+    coords: impl Iterator<Item = Result<[f64; 2], Error>>,
+    method: LengthMethod,
+) -> Result<f64, Error> {
+    let mut total = 0.0;
+    let mut previous = None;
+    for (idx, coord) in coords.enumerate() {
+        let coord = coord?;
+        if let Some(previous) = previous.replace(coord) {
+            total += distance(previous, coord, method, idx - 1)?;
+        }
+    }
+    Ok(total)
+}
+
+// The great-circle distance in metres from the first of a sequence of [lon, lat] coordinates to each of them
+fn cumulative_distances(coords: &[[f64; 2]]) -> Vec<f64> {
+    let along = coords.windows(2).scan(0.0, |total, pair| {
+        *total += Haversine.distance(Point::from(pair[0]), Point::from(pair[1]));
+        Some(*total)
+    });
+    iter::once(0.0).chain(along).take(coords.len()).collect()
+}
+
+// Find the [lon, lat] coordinate at a distance along a sequence of coordinates, following great circles,
+// and the index of the segment containing it. Segment i runs from coordinate i to coordinate i + 1
+fn interpolate(
+    coords: &[[f64; 2]],
+    distance: f64,
+    mode: InterpolationMode,
+) -> Result<([f64; 2], usize), Error> {
+    if coords.len() < 2 {
+        return Err(Error::Argument(
+            "interpolating requires at least two points",
+        ));
+    }
+    let cumulative = cumulative_distances(coords);
+    let total = cumulative[cumulative.len() - 1];
+    let target = match mode {
+        InterpolationMode::Fraction if (0.0..=1.0).contains(&distance) => distance * total,
+        InterpolationMode::Metres if (0.0..=total).contains(&distance) => distance,
+        InterpolationMode::Fraction => {
+            return Err(Error::Argument("fraction must be between 0 and 1"));
+        }
+        InterpolationMode::Metres => {
+            return Err(Error::Argument(
+                "distance must be between 0 and the Polyline's length",
+            ));
+        }
+    };
+    // the first segment which ends at or beyond the target
+    let segment = cumulative[1..]
+        .partition_point(|&d| d < target)
+        .min(coords.len() - 2);
+    let start = cumulative[segment];
+    let span = cumulative[segment + 1] - start;
+    let ratio = if span > 0.0 {
+        ((target - start) / span).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let point = Haversine.point_at_ratio_between(
+        Point::from(coords[segment]),
+        Point::from(coords[segment + 1]),
+        ratio,
+    );
+    Ok((point.into(), segment))
+}
+
+/// Measure the length of a Polyline, in metres
+///
+/// Callers must pass four arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a [LengthMethod](enum.LengthMethod.html) value as an unsigned 32-bit `int`
+/// - a pointer to a `double`, which will receive the length
+///
+/// The Polyline is decoded one coordinate at a time, without allocating.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// The status is `NoConvergence` if Vincenty's formulae failed to converge, with the index of the first point of the segment.
+/// The status is `InvalidArgument` if `method` isn't a `LengthMethod` value.
+/// On failure, `out_length` will be set to `0`.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_length_m(
+    pl: *const c_char,
+    precision: u32,
+    method: u32,
+    out_length: *mut f64,
+) -> PolylineResult {
+    if out_length.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let method = enum_arg(method, "unknown length method")?;
+        let incoming = str_from_ptr(pl)?;
+        let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+        length(codec::coords(incoming.as_bytes(), precision), method)
+    });
+    out_length.write(*result.as_ref().unwrap_or(&0.0));
+    report(result.map(|_| ()))
+}
+
+/// Measure the length of an array of coordinates, in metres
+///
+/// Callers must pass four arguments:
+///
+/// - a [Struct](struct.ExternalArray.html) with two fields:
+///     - `data`, a void pointer to an array of floating-point coordinate pairs, in the order given by `order`
+///     - `len`, the length of the array being passed. Its type must be `size_t`
+/// - a [CoordinateOrder](enum.CoordinateOrder.html) value as an unsigned 32-bit `int`, specifying the order of each
+///   coordinate pair in `coords`
+/// - a [LengthMethod](enum.LengthMethod.html) value as an unsigned 32-bit `int`
+/// - a pointer to a `double`, which will receive the length
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`encode_coordinates_ffi_checked`](fn.encode_coordinates_ffi_checked.html).
+/// The status is `NoConvergence` if Vincenty's formulae failed to converge, with the index of the first point of the segment.
+/// The status is `InvalidArgument` if `order` isn't a `CoordinateOrder` value, or `method` isn't a `LengthMethod` value.
+/// On failure, `out_length` will be set to `0`.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn coordinates_length_m(
+    coords: ExternalArray,
+    order: u32,
+    method: u32,
+    out_length: *mut f64,
+) -> PolylineResult {
+    if out_length.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let order: CoordinateOrder = enum_arg(order, "unknown coordinate order")?;
+        let method = enum_arg(method, "unknown length method")?;
+        let coords = coords.as_slice()?.iter().enumerate().map(|(idx, &coord)| {
+            let coord = order.apply(coord);
+            check_coord(coord, idx).map(|_| coord)
+        });
+        length(coords, method)
+    });
+    out_length.write(*result.as_ref().unwrap_or(&0.0));
+    report(result.map(|_| ()))
+}
+
+/// Find the coordinate at a distance along a Polyline
+///
+/// Callers must pass six arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a `double` distance along the Polyline, whose meaning depends on `mode`
+/// - an [InterpolationMode](enum.InterpolationMode.html) value as an unsigned 32-bit `int`
+/// - a pointer to two `double` values, which will receive the lon, lat coordinate
+/// - a pointer to a `size_t`, which will receive the index of the segment containing the coordinate.
+///   Segment `i` runs from point `i` to point `i + 1`
+///
+/// Distances are measured along great circles, using the haversine formula, and the coordinate is interpolated
+/// along the great circle between the segment's points.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// The status is `InvalidArgument` if the Polyline has fewer than two points, the distance is outside it, or `mode`
+/// isn't an `InterpolationMode` value.
+/// On failure, `out_coord` and `out_segment` will be set to `0`.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_interpolate(
+    pl: *const c_char,
+    precision: u32,
+    distance: f64,
+    mode: u32,
+    out_coord: *mut f64,
+    out_segment: *mut libc::size_t,
+) -> PolylineResult {
+    if out_coord.is_null() || out_segment.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let mode = enum_arg(mode, "unknown interpolation mode")?;
+        let coords = try_vec_from_string(str_from_ptr(pl)?, precision, CoordinateOrder::LonLat)?;
+        interpolate(&coords, distance, mode)
+    });
+    let (coord, segment) = *result.as_ref().unwrap_or(&([0.0, 0.0], 0));
+    out_coord.cast::<[f64; 2]>().write(coord);
+    out_segment.write(segment);
+    report(result.map(|_| ()))
+}
+
+/// Find the distance along a Polyline to each of its points, in metres
+///
+/// Callers must pass three arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a pointer to a [DoubleArray](type.DoubleArray.html), which will receive the distances
+///
+/// The array has one distance per decoded point, the first of which is `0.0`.
+/// Distances are measured along great circles, using the haversine formula.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// On failure, `out` will receive an empty array.
+///
+/// Implementations calling this function **must** call [`drop_double_array`](fn.drop_double_array.html)
+/// with the array written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_cumulative_distances(
+    pl: *const c_char,
+    precision: u32,
+    out: *mut DoubleArray,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let coords = try_vec_from_string(str_from_ptr(pl)?, precision, CoordinateOrder::LonLat)?;
+        Ok(cumulative_distances(&coords))
+    });
+    let (result, distances) = match result {
+        Ok(distances) => (Ok(()), distances.into()),
+        Err(e) => (Err(e), DoubleArray::empty()),
+    };
+    out.write(distances);
+    report(result)
+}
+
+/// Free [DoubleArray](type.DoubleArray.html) memory which Rust has allocated across the FFI boundary
+#[no_mangle]
+pub extern "C" fn drop_double_array(arr: DoubleArray) {
+    let _ = catch_panic(|| {
+        drop(arr);
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PolylineStatus;
+    use std::ffi::CString;
+    use std::{ptr, slice};
+
+    #[test]
+    fn test_length() {
+        // a degree of longitude at the equator
+        let equator = [[0.0, 0.0], [1.0, 0.0]];
+        let cases = [
+            (LengthMethod::Haversine, 111_195.08),
+            (LengthMethod::Vincenty, 111_319.49),
+            (LengthMethod::Geodesic, 111_319.49),
+        ];
+        for (method, expected) in cases {
+            let mut measured = 0.0;
+            let coords: ExternalArray = equator.to_vec().into();
+            let res = unsafe {
+                coordinates_length_m(
+                    coords,
+                    CoordinateOrder::LonLat as u32,
+                    method as u32,
+                    &mut measured,
+                )
+            };
+            assert_eq!(res, PolylineResult::ok());
+            assert!(
+                (measured - expected).abs() < 0.01,
+                "{:?}: {}",
+                method,
+                measured
+            );
+        }
+
+        // the same route, as a Polyline and in lat, lon order
+        let input: &str = include!("../test_fixtures/berlin_decoded.rs");
+        let pl = CString::new(input).unwrap();
+        let swapped: Vec<[f64; 2]> = codec::coords(input.as_bytes(), 5)
+            .map(|c| CoordinateOrder::LatLon.apply(c.unwrap()))
+            .collect();
+        for (method, _) in cases {
+            let (mut from_pl, mut from_coords) = (0.0, 0.0);
+            let coords: ExternalArray = swapped.clone().into();
+            unsafe {
+                polyline_length_m(pl.as_ptr(), 5, method as u32, &mut from_pl);
+                coordinates_length_m(
+                    coords,
+                    CoordinateOrder::LatLon as u32,
+                    method as u32,
+                    &mut from_coords,
+                );
+            }
+            assert!(from_pl > 0.0);
+            assert_eq!(from_pl, from_coords);
+        }
+    }
+
+    #[test]
+    fn test_length_failures() {
+        // nearly antipodal, after a first segment
+        let coords: ExternalArray = vec![[1.0, 4.0], [2.0, 4.0], [-178.0, -4.0]].into();
+        let mut measured = 1.0;
+        let res = unsafe {
+            coordinates_length_m(
+                coords,
+                CoordinateOrder::LonLat as u32,
+                LengthMethod::Vincenty as u32,
+                &mut measured,
+            )
+        };
+        assert_eq!(res.status, PolylineStatus::NoConvergence);
+        assert_eq!(res.position, 1);
+        assert_eq!(measured, 0.0);
+
+        let coords: ExternalArray = vec![[1.0, 4.0], [2.0, 4.0]].into();
+        let res = unsafe {
+            coordinates_length_m(
+                coords,
+                CoordinateOrder::LatLon as u32,
+                LengthMethod::Haversine as u32,
+                &mut measured,
+            )
+        };
+        assert_eq!(res, PolylineResult::ok());
+        let res = unsafe {
+            polyline_length_m(
+                ptr::null(),
+                5,
+                LengthMethod::Haversine as u32,
+                &mut measured,
+            )
+        };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+
+        // unknown enum values
+        let pl = CString::new(codec::encode_fixture(&[[1.0, 4.0], [2.0, 4.0]], 5)).unwrap();
+        let res = unsafe { polyline_length_m(pl.as_ptr(), 5, 3, &mut measured) };
+        assert_eq!(res.status, PolylineStatus::InvalidArgument);
+        assert_eq!(measured, 0.0);
+        for (order, method) in [(2, LengthMethod::Haversine as u32), (0, 3)] {
+            let coords: ExternalArray = vec![[1.0, 4.0], [2.0, 4.0]].into();
+            measured = 1.0;
+            let res = unsafe { coordinates_length_m(coords, order, method, &mut measured) };
+            assert_eq!(res.status, PolylineStatus::InvalidArgument);
+            assert_eq!(measured, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_cumulative_distances() {
+        let input: &str = include!("../test_fixtures/berlin_decoded.rs");
+        let pl = CString::new(input).unwrap();
+        let mut out = DoubleArray::empty();
+        let res = unsafe { polyline_cumulative_distances(pl.as_ptr(), 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        let distances = unsafe { slice::from_raw_parts(out.data, out.len) };
+        assert_eq!(distances.len(), codec::coords(input.as_bytes(), 5).count());
+        assert_eq!(distances[0], 0.0);
+        assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+        let mut length = 0.0;
+        unsafe { polyline_length_m(pl.as_ptr(), 5, LengthMethod::Haversine as u32, &mut length) };
+        assert!((distances[distances.len() - 1] - length).abs() < 1e-6);
+        drop_double_array(out);
+
+        let mut out = DoubleArray::empty();
+        let res = unsafe { polyline_cumulative_distances(ptr::null(), 5, &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        assert_eq!(out.len, 0);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        // two degrees along the equator, so the midpoint of each segment is easy to find
+        let pl = CString::new(codec::encode_fixture(
+            &[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]],
+            5,
+        ))
+        .unwrap();
+        let degree = Haversine.distance(Point::new(0.0, 0.0), Point::new(1.0, 0.0));
+        let cases = [
+            (0.25, InterpolationMode::Fraction, [0.5, 0.0], 0),
+            (1.0, InterpolationMode::Fraction, [2.0, 0.0], 1),
+            (degree * 1.5, InterpolationMode::Metres, [1.5, 0.0], 1),
+            (0.0, InterpolationMode::Metres, [0.0, 0.0], 0),
+        ];
+        for (distance, mode, expected, expected_segment) in cases {
+            let mut coord = [f64::NAN; 2];
+            let mut segment = usize::MAX;
+            let res = unsafe {
+                polyline_interpolate(
+                    pl.as_ptr(),
+                    5,
+                    distance,
+                    mode as u32,
+                    coord.as_mut_ptr(),
+                    &mut segment,
+                )
+            };
+            assert_eq!(res, PolylineResult::ok());
+            assert!((coord[0] - expected[0]).abs() < 1e-9, "{:?}", coord);
+            assert!((coord[1] - expected[1]).abs() < 1e-9, "{:?}", coord);
+            assert_eq!(segment, expected_segment);
+        }
+
+        let mut coord = [1.0; 2];
+        let mut segment = 1;
+        for (distance, mode) in [
+            (1.5, InterpolationMode::Fraction),
+            (f64::NAN, InterpolationMode::Fraction),
+            (degree * 3.0, InterpolationMode::Metres),
+        ] {
+            let res = unsafe {
+                polyline_interpolate(
+                    pl.as_ptr(),
+                    5,
+                    distance,
+                    mode as u32,
+                    coord.as_mut_ptr(),
+                    &mut segment,
+                )
+            };
+            assert_eq!(res.status, PolylineStatus::InvalidArgument);
+            assert_eq!((coord, segment), ([0.0, 0.0], 0));
+        }
+        (coord, segment) = ([1.0; 2], 1);
+        let res = unsafe {
+            polyline_interpolate(pl.as_ptr(), 5, 0.5, 2, coord.as_mut_ptr(), &mut segment)
+        };
+        assert_eq!(res.status, PolylineStatus::InvalidArgument);
+        assert_eq!((coord, segment), ([0.0, 0.0], 0));
+        assert_eq!(
+            interpolate(&[[1.0, 1.0]], 0.0, InterpolationMode::Fraction)
+                .unwrap_err()
+                .status(),
+            PolylineStatus::InvalidArgument
+        );
+    }
+}
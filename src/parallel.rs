@@ -0,0 +1,214 @@
+//! Batch entry points which spread their work across a thread pool
+//!
+//! These are only available when the crate is built with the `parallel` feature.
+
+use crate::batch::{self, decode_item, encode_item, BatchResults, DecodedBatch, EncodedBatch};
+use crate::error::{catch_panic, report, Error, PolylineResult};
+use crate::ExternalArray;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::{Arc, PoisonError, RwLock};
+
+// The pool used by the parallel entry points, which is created on first use unless one has been configured
+static POOL: RwLock<Option<Arc<ThreadPool>>> = RwLock::new(None);
+
+// Build a pool with num_threads threads. 0 lets rayon choose, which usually means one per core
+fn build_pool(num_threads: usize) -> Result<Arc<ThreadPool>, Error> {
+    ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map(Arc::new)
+        .map_err(|e| Error::ThreadPool(e.to_string()))
+}
+
+fn pool() -> Result<Arc<ThreadPool>, Error> {
+    if let Some(pool) = POOL.read().unwrap_or_else(PoisonError::into_inner).as_ref() {
+        return Ok(Arc::clone(pool));
+    }
+    let mut configured = POOL.write().unwrap_or_else(PoisonError::into_inner);
+    // another thread may have created the pool while we were waiting
+    if let Some(pool) = configured.as_ref() {
+        return Ok(Arc::clone(pool));
+    }
+    let pool = build_pool(0)?;
+    *configured = Some(Arc::clone(&pool));
+    Ok(pool)
+}
+
+// Decode the items of a batch on the pool. Collecting an indexed parallel iterator preserves input order
+// If the pool can't be created, the whole batch fails
+fn decode_items(items: Vec<Result<&str, Error>>, precision: u32) -> BatchResults<Vec<[f64; 2]>> {
+    Ok(pool()?.install(|| {
+        items
+            .into_par_iter()
+            .map(|item| decode_item(item, precision))
+            .collect()
+    }))
+}
+
+// Encode the items of a batch on the pool. Collecting an indexed parallel iterator preserves input order
+// If the pool can't be created, the whole batch fails
+fn encode_items(items: Vec<&[[f64; 2]]>, precision: u32) -> BatchResults<Vec<u8>> {
+    Ok(pool()?.install(|| {
+        items
+            .into_par_iter()
+            .map(|item| encode_item(item, precision))
+            .collect()
+    }))
+}
+
+/// Set the maximum number of threads used by the parallel batch functions
+///
+/// Pass `0` to use the default, which is usually one thread per CPU core.
+/// Calls which are already running will finish on the previous threads.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), whose `status` is `ThreadPoolError` if the threads couldn't be created.
+///
+/// Only available when built with the `parallel` feature.
+#[no_mangle]
+pub extern "C" fn polyline_set_num_threads(num_threads: libc::size_t) -> PolylineResult {
+    report(catch_panic(|| {
+        let pool = build_pool(num_threads)?;
+        *POOL.write().unwrap_or_else(PoisonError::into_inner) = Some(pool);
+        Ok(())
+    }))
+}
+
+/// Convert a batch of length-delimited Polylines into coordinates, spreading the work across multiple threads
+///
+/// Takes the same arguments, and produces the same output, as [`decode_polylines_batch`](fn.decode_polylines_batch.html).
+/// The results are in input order, and are identical to those of the sequential function.
+/// If the thread pool can't be created, the returned status is `ThreadPoolError`, and the batch is empty.
+///
+/// Implementations calling this function **must** call [`drop_decoded_batch`](fn.drop_decoded_batch.html)
+/// with the batch written to `out`, in order to free the memory it allocates.
+///
+/// Only available when built with the `parallel` feature.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decode_polylines_batch_parallel(
+    pls: *const *const u8,
+    lens: *const libc::size_t,
+    count: libc::size_t,
+    precision: u32,
+    out: *mut DecodedBatch,
+) -> PolylineResult {
+    batch::decode_batch(pls, lens, count, precision, out, decode_items)
+}
+
+/// Convert a batch of coordinate sequences into Polylines, spreading the work across multiple threads
+///
+/// Takes the same arguments, and produces the same output, as [`encode_coordinates_batch`](fn.encode_coordinates_batch.html).
+/// The results are in input order, and are identical to those of the sequential function.
+/// If the thread pool can't be created, the returned status is `ThreadPoolError`, and the batch is empty.
+///
+/// Implementations calling this function **must** call [`drop_encoded_batch`](fn.drop_encoded_batch.html)
+/// with the batch written to `out`, in order to free the memory it allocates.
+///
+/// Only available when built with the `parallel` feature.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encode_coordinates_batch_parallel(
+    coords: ExternalArray,
+    offsets: *const libc::size_t,
+    count: libc::size_t,
+    precision: u32,
+    out: *mut EncodedBatch,
+) -> PolylineResult {
+    batch::encode_batch(coords, offsets, count, precision, out, encode_items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_polylines_batch, encode_coordinates_batch};
+    use std::slice;
+
+    // the Berlin fixture, repeated, with a bad item every 100 items
+    fn fixture() -> (Vec<[f64; 2]>, Vec<usize>) {
+        let berlin: Vec<[f64; 2]> = include!("../test_fixtures/berlin.rs");
+        let mut coords = vec![];
+        let mut offsets = vec![0];
+        for i in 0..1000 {
+            coords.extend_from_slice(&berlin[..(i % berlin.len()) + 1]);
+            if i % 100 == 0 {
+                coords.push([0.0, 91.0]);
+            }
+            offsets.push(coords.len());
+        }
+        (coords, offsets)
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let (coords, offsets) = fixture();
+        let count = offsets.len() - 1;
+        let mut sequential = unsafe { std::mem::zeroed::<EncodedBatch>() };
+        let mut parallel = unsafe { std::mem::zeroed::<EncodedBatch>() };
+        assert_eq!(polyline_set_num_threads(4), PolylineResult::ok());
+        unsafe {
+            let input: ExternalArray = coords.clone().into();
+            encode_coordinates_batch(input, offsets.as_ptr(), count, 5, &mut sequential);
+            let input: ExternalArray = coords.into();
+            let res =
+                encode_coordinates_batch_parallel(input, offsets.as_ptr(), count, 5, &mut parallel);
+            assert_eq!(res, PolylineResult::ok());
+            assert_eq!(
+                slice::from_raw_parts(sequential.data, sequential.len),
+                slice::from_raw_parts(parallel.data, parallel.len)
+            );
+            assert_eq!(
+                slice::from_raw_parts(sequential.offsets, count + 1),
+                slice::from_raw_parts(parallel.offsets, count + 1)
+            );
+            assert_eq!(
+                slice::from_raw_parts(sequential.statuses, count),
+                slice::from_raw_parts(parallel.statuses, count)
+            );
+        }
+
+        // now decode the encoded batch
+        let encoded_offsets = unsafe { slice::from_raw_parts(parallel.offsets, count + 1) };
+        let pls: Vec<*const u8> = encoded_offsets[..count]
+            .iter()
+            .map(|&o| unsafe { parallel.data.cast::<u8>().add(o).cast_const() })
+            .collect();
+        let lens: Vec<usize> = encoded_offsets.windows(2).map(|w| w[1] - w[0]).collect();
+        let mut sequential_decoded = unsafe { std::mem::zeroed::<DecodedBatch>() };
+        let mut parallel_decoded = unsafe { std::mem::zeroed::<DecodedBatch>() };
+        unsafe {
+            decode_polylines_batch(
+                pls.as_ptr(),
+                lens.as_ptr(),
+                count,
+                5,
+                &mut sequential_decoded,
+            );
+            let res = decode_polylines_batch_parallel(
+                pls.as_ptr(),
+                lens.as_ptr(),
+                count,
+                5,
+                &mut parallel_decoded,
+            );
+            assert_eq!(res, PolylineResult::ok());
+            let len = sequential_decoded.coords.len;
+            assert_eq!(len, parallel_decoded.coords.len);
+            assert_eq!(
+                slice::from_raw_parts(sequential_decoded.coords.data.cast::<[f64; 2]>(), len),
+                slice::from_raw_parts(parallel_decoded.coords.data.cast::<[f64; 2]>(), len)
+            );
+            assert_eq!(
+                slice::from_raw_parts(sequential_decoded.offsets, count + 1),
+                slice::from_raw_parts(parallel_decoded.offsets, count + 1)
+            );
+        }
+        assert_eq!(polyline_set_num_threads(0), PolylineResult::ok());
+    }
+}
@@ -0,0 +1,295 @@
+//! Entry points which simplify Polylines and coordinate arrays, using the algorithms provided by the `geo` crate
+
+use crate::codec::{self, check_coord};
+use crate::error::{catch_panic, report, Error, PolylineResult};
+use crate::{enum_arg, str_from_ptr, try_vec_from_string, write_array, write_string};
+use crate::{CoordinateOrder, ExternalArray, InternalArray, OwnedSlice};
+use geo::{SimplifyIdx, SimplifyVwIdx, SimplifyVwPreserve};
+use geo_types::LineString;
+use libc::c_char;
+
+/// The line simplification algorithm to use
+///
+/// - `DouglasPeucker` removes points which are closer than the tolerance to the simplified line.
+///   The tolerance is a distance, in degrees
+/// - `VisvalingamWhyatt` removes points which form a triangle with their neighbours whose area is smaller than the tolerance.
+///   The tolerance is an area, in square degrees
+/// - `VisvalingamWhyattPreserve` is a variant of `VisvalingamWhyatt` which keeps points whose removal would
+///   cause the simplified line to intersect itself. It is slower
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimplifyAlgorithm {
+    DouglasPeucker = 0,
+    VisvalingamWhyatt,
+    VisvalingamWhyattPreserve,
+}
+
+impl TryFrom<u32> for SimplifyAlgorithm {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SimplifyAlgorithm::DouglasPeucker),
+            1 => Ok(SimplifyAlgorithm::VisvalingamWhyatt),
+            2 => Ok(SimplifyAlgorithm::VisvalingamWhyattPreserve),
+            _ => Err(value),
+        }
+    }
+}
+
+/// A C-compatible `struct` originating **inside** Rust, holding indices into an array of coordinates
+///
+/// `data` points to `len` `size_t` values, in ascending order.
+pub type IndexArray = OwnedSlice<libc::size_t>;
+
+// Find the indices of the points of a line which were retained by simplifying it.
+// Simplification only removes points, so the simplified points appear in the same order
+fn retained_indices(line: &LineString, simplified: &LineString) -> Vec<usize> {
+    let mut candidates = line.coords().enumerate();
+    simplified
+        .coords()
+        .filter_map(|retained| candidates.find(|(_, c)| *c == retained).map(|(idx, _)| idx))
+        .collect()
+}
+
+// Simplify [lon, lat] coordinates, returning the retained coordinates and their indices
+fn simplify(
+    coords: Vec<[f64; 2]>,
+    tolerance: f64,
+    algorithm: SimplifyAlgorithm,
+) -> Result<(Vec<[f64; 2]>, Vec<usize>), Error> {
+    if !(tolerance.is_finite() && tolerance >= 0.0) {
+        return Err(Error::Argument("tolerance must be finite and non-negative"));
+    }
+    let line = LineString::from(coords);
+    let indices = match algorithm {
+        SimplifyAlgorithm::DouglasPeucker => line.simplify_idx(tolerance),
+        SimplifyAlgorithm::VisvalingamWhyatt => line.simplify_vw_idx(tolerance),
+        SimplifyAlgorithm::VisvalingamWhyattPreserve => {
+            retained_indices(&line, &line.simplify_vw_preserve(tolerance))
+        }
+    };
+    let retained = indices.iter().map(|&idx| line[idx].into()).collect();
+    Ok((retained, indices))
+}
+
+// Write the retained indices of a simplification call to out_indices, if it isn't NULL, returning the rest of the result.
+// out_indices receives an empty array on failure
+unsafe fn write_indices<T>(
+    out_indices: *mut IndexArray,
+    result: Result<(T, Vec<usize>), Error>,
+) -> Result<T, Error> {
+    let (result, indices) = match result {
+        Ok((simplified, indices)) => (Ok(simplified), indices.into()),
+        Err(e) => (Err(e), IndexArray::empty()),
+    };
+    if !out_indices.is_null() {
+        out_indices.write(indices);
+    }
+    result
+}
+
+/// Simplify a Polyline
+///
+/// Callers must pass six arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a `double` tolerance, whose meaning depends on the algorithm. It must be finite, and not negative
+/// - a [SimplifyAlgorithm](enum.SimplifyAlgorithm.html) value as an unsigned 32-bit `int`
+/// - a pointer to a `char*`, which will receive the simplified Polyline, at the same precision
+/// - a pointer to an [IndexArray](type.IndexArray.html), which will receive the indices of the retained points,
+///   or `NULL` if they aren't needed
+///
+/// The simplified Polyline only contains points from the input, so its coordinates are unchanged.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// The status is `InvalidArgument` if the tolerance is negative or not finite, or if `algorithm` isn't a
+/// `SimplifyAlgorithm` value.
+///
+/// Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+/// with a non-`NULL` pointer written to `out`, and [`drop_index_array`](fn.drop_index_array.html)
+/// with any array written to `out_indices`, in order to free the memory they allocate.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_simplify(
+    pl: *const c_char,
+    precision: u32,
+    tolerance: f64,
+    algorithm: u32,
+    out: *mut *mut c_char,
+    out_indices: *mut IndexArray,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let algorithm = enum_arg(algorithm, "unknown simplification algorithm")?;
+        let coords = try_vec_from_string(str_from_ptr(pl)?, precision, CoordinateOrder::LonLat)?;
+        let (retained, indices) = simplify(coords, tolerance, algorithm)?;
+        let mut encoded = String::new();
+        codec::encode_coords(&retained, precision, &mut |b| encoded.push(char::from(b)))?;
+        Ok((encoded, indices))
+    });
+    write_string(out, write_indices(out_indices, result))
+}
+
+/// Simplify an array of coordinates
+///
+/// Callers must pass five arguments:
+///
+/// - a [Struct](struct.ExternalArray.html) with two fields:
+///     - `data`, a void pointer to an array of floating-point lon, lat coordinates: `[[2.0, 1.0]]`
+///     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
+/// - a `double` tolerance, whose meaning depends on the algorithm. It must be finite, and not negative
+/// - a [SimplifyAlgorithm](enum.SimplifyAlgorithm.html) value as an unsigned 32-bit `int`
+/// - a pointer to an [InternalArray](struct.InternalArray.html), which will receive the simplified coordinates
+/// - a pointer to an [IndexArray](type.IndexArray.html), which will receive the indices of the retained coordinates,
+///   or `NULL` if they aren't needed
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`encode_coordinates_ffi_checked`](fn.encode_coordinates_ffi_checked.html).
+/// The status is `InvalidArgument` if the tolerance is negative or not finite, or if `algorithm` isn't a
+/// `SimplifyAlgorithm` value.
+///
+/// Implementations calling this function **must** call [`drop_float_array`](fn.drop_float_array.html)
+/// with the array written to `out`, and [`drop_index_array`](fn.drop_index_array.html)
+/// with any array written to `out_indices`, in order to free the memory they allocate.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn simplify_coordinates(
+    coords: ExternalArray,
+    tolerance: f64,
+    algorithm: u32,
+    out: *mut InternalArray,
+    out_indices: *mut IndexArray,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let algorithm = enum_arg(algorithm, "unknown simplification algorithm")?;
+        let coords = coords.as_slice()?;
+        for (idx, &coord) in coords.iter().enumerate() {
+            check_coord(coord, idx)?;
+        }
+        let (retained, indices) = simplify(coords.to_vec(), tolerance, algorithm)?;
+        Ok((retained.into(), indices))
+    });
+    write_array(out, write_indices(out_indices, result))
+}
+
+/// Free [IndexArray](type.IndexArray.html) memory which Rust has allocated across the FFI boundary
+#[no_mangle]
+pub extern "C" fn drop_index_array(arr: IndexArray) {
+    let _ = catch_panic(|| {
+        drop(arr);
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{drop_cstring, PolylineStatus};
+    use std::ffi::{CStr, CString};
+    use std::{ptr, slice};
+
+    #[test]
+    fn test_simplify_coordinates() {
+        // the example from the geo documentation
+        let input = vec![
+            [0.0, 0.0],
+            [5.0, 4.0],
+            [11.0, 5.5],
+            [17.3, 3.2],
+            [27.8, 0.1],
+        ];
+        let coords: ExternalArray = input.clone().into();
+        let mut out = InternalArray::empty();
+        let mut indices = IndexArray::empty();
+        let res = unsafe {
+            simplify_coordinates(
+                coords,
+                1.0,
+                SimplifyAlgorithm::DouglasPeucker as u32,
+                &mut out,
+                &mut indices,
+            )
+        };
+        assert_eq!(res, PolylineResult::ok());
+        let indices = unsafe { slice::from_raw_parts(indices.data, indices.len) };
+        assert_eq!(indices, [0, 1, 2, 4]);
+        let simplified = unsafe { slice::from_raw_parts(out.data.cast::<[f64; 2]>(), out.len) };
+        let expected: Vec<[f64; 2]> = indices.iter().map(|&i| input[i]).collect();
+        assert_eq!(simplified, expected);
+
+        let coords: ExternalArray = input.clone().into();
+        let res = unsafe {
+            simplify_coordinates(
+                coords,
+                -1.0,
+                SimplifyAlgorithm::VisvalingamWhyatt as u32,
+                &mut out,
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(res.status, PolylineStatus::InvalidArgument);
+        assert_eq!(out.len, 0);
+
+        let coords: ExternalArray = input.into();
+        let res = unsafe { simplify_coordinates(coords, 1.0, 3, &mut out, ptr::null_mut()) };
+        assert_eq!(res.status, PolylineStatus::InvalidArgument);
+        assert_eq!(out.len, 0);
+    }
+
+    #[test]
+    fn test_simplify_polyline() {
+        let input: &str = include!("../test_fixtures/berlin_decoded.rs");
+        let pl = CString::new(input).unwrap();
+        let original: Vec<[f64; 2]> = codec::coords(input.as_bytes(), 5)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for algorithm in [
+            SimplifyAlgorithm::DouglasPeucker,
+            SimplifyAlgorithm::VisvalingamWhyatt,
+            SimplifyAlgorithm::VisvalingamWhyattPreserve,
+        ] {
+            let mut out = ptr::null_mut();
+            let mut indices = IndexArray::empty();
+            let res = unsafe {
+                polyline_simplify(
+                    pl.as_ptr(),
+                    5,
+                    0.0001,
+                    algorithm as u32,
+                    &mut out,
+                    &mut indices,
+                )
+            };
+            assert_eq!(res, PolylineResult::ok());
+            let simplified = unsafe { CStr::from_ptr(out) }.to_str().unwrap();
+            let decoded: Vec<[f64; 2]> = codec::coords(simplified.as_bytes(), 5)
+                .collect::<Result<_, _>>()
+                .unwrap();
+            let indices_slice = unsafe { slice::from_raw_parts(indices.data, indices.len) };
+            // the retained points are a subset of the original, and the indices identify them
+            assert!(decoded.len() < original.len());
+            let expected: Vec<[f64; 2]> = indices_slice.iter().map(|&i| original[i]).collect();
+            assert_eq!(decoded, expected, "{:?}", algorithm);
+            unsafe { drop_cstring(out) };
+            drop_index_array(indices);
+        }
+        let mut out = ptr::null_mut();
+        let res =
+            unsafe { polyline_simplify(pl.as_ptr(), 5, 0.0001, 3, &mut out, ptr::null_mut()) };
+        assert_eq!(res.status, PolylineStatus::InvalidArgument);
+        assert!(out.is_null());
+    }
+}
@@ -0,0 +1,575 @@
+//! Handles which decode or encode a Polyline a piece at a time, keeping the running coordinate totals between calls
+
+use crate::buffers::out_slice;
+use crate::codec::{check_coord, factor, unscale_lat, PointEncoder, ScaledPoint, ValueDecoder};
+use crate::error::{catch_panic, report, update_last_error, Error, PolylineResult};
+use crate::{enum_arg, get_precision, input_slice, write_string, CoordinateOrder, ExternalArray};
+use libc::c_char;
+use polyline::errors::PolylineError;
+use std::collections::VecDeque;
+use std::ptr;
+
+/// An opaque handle to a streaming Polyline decoder
+///
+/// Created by [`decoder_new`](fn.decoder_new.html), and freed by [`decoder_free`](fn.decoder_free.html).
+/// A decoder holds the running coordinate totals between calls, so a Polyline may be split at any byte.
+pub struct PolylineDecoder {
+    factor: i64,
+    // the number of bytes fed so far, which is the offset of the next byte in the whole input
+    pos: usize,
+    value: ValueDecoder,
+    // the offset at which the value being decoded began
+    value_idx: usize,
+    // the offset at which a latitude began, if its longitude hasn't been decoded yet
+    pending: Option<usize>,
+    lat: i64,
+    lon: i64,
+    // decoded [lon, lat] pairs which haven't yet been retrieved
+    ready: VecDeque<[f64; 2]>,
+    // once a decoder has failed, it keeps reporting the same failure
+    failed: Option<Error>,
+}
+
+impl PolylineDecoder {
+    fn new(precision: u32) -> Result<Self, Error> {
+        let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+        Ok(PolylineDecoder {
+            factor: factor(precision),
+            pos: 0,
+            value: ValueDecoder::default(),
+            value_idx: 0,
+            pending: None,
+            lat: 0,
+            lon: 0,
+            ready: VecDeque::new(),
+            failed: None,
+        })
+    }
+
+    fn check_failed(&self) -> Result<(), Error> {
+        self.failed.as_ref().map_or(Ok(()), |e| Err(e.clone()))
+    }
+
+    // Decode the next piece of the input, queueing every coordinate it completes
+    fn feed(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.check_failed()?;
+        let result = bytes.iter().try_for_each(|&byte| self.push(byte));
+        if let Err(e) = &result {
+            self.failed = Some(e.clone());
+        }
+        result
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), Error> {
+        let idx = self.pos;
+        self.pos += 1;
+        if !self.value.is_partial() {
+            self.value_idx = idx;
+        }
+        let Some(value) = self.value.push(idx, byte)? else {
+            return Ok(());
+        };
+        // as in codec::ScaledPoints, saturating keeps out-of-range sums out of range,
+        // and a latitude is checked as soon as it's decoded
+        match self.pending.take() {
+            None => {
+                self.lat = self.lat.saturating_add(value);
+                unscale_lat(self.lat, self.factor, self.value_idx)?;
+                self.pending = Some(self.value_idx);
+            }
+            Some(lat_idx) => {
+                self.lon = self.lon.saturating_add(value);
+                let point = ScaledPoint {
+                    lat: self.lat,
+                    lon: self.lon,
+                    lat_idx,
+                    lon_idx: self.value_idx,
+                };
+                self.ready.push_back(point.to_coord(self.factor)?);
+            }
+        }
+        Ok(())
+    }
+
+    // Move as many queued coordinates as will fit into buf, returning the number moved
+    fn next_points(&mut self, buf: &mut [[f64; 2]]) -> usize {
+        let len = buf.len().min(self.ready.len());
+        for (slot, coord) in buf.iter_mut().zip(self.ready.drain(..len)) {
+            *slot = coord;
+        }
+        len
+    }
+
+    // Check that the input fed so far ended on a complete coordinate
+    fn finish(&self) -> Result<(), Error> {
+        self.check_failed()?;
+        if self.value.is_partial() {
+            return Err(Error::Truncated {
+                idx: self.value_idx,
+            });
+        }
+        if let Some(idx) = self.pending {
+            return Err(PolylineError::NoLongError { idx }.into());
+        }
+        Ok(())
+    }
+}
+
+// Borrow a decoder handle, which must not be NULL
+unsafe fn decoder_mut<'a>(decoder: *mut PolylineDecoder) -> Result<&'a mut PolylineDecoder, Error> {
+    decoder.as_mut().ok_or(Error::NullPointer)
+}
+
+/// Create a streaming Polyline decoder
+///
+/// Callers must pass an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+/// OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html)).
+///
+/// Returns a pointer to a new decoder, or `NULL` if the precision is invalid.
+/// Input is passed to the decoder using [`decoder_feed`](fn.decoder_feed.html), and the decoded coordinates are retrieved
+/// using [`decoder_next_points`](fn.decoder_next_points.html).
+/// Once all the input has been fed, [`decoder_finish`](fn.decoder_finish.html) checks that it was complete.
+///
+/// Implementations calling this function **must** call [`decoder_free`](fn.decoder_free.html)
+/// with the returned pointer, in order to free the memory it allocates.
+#[no_mangle]
+pub extern "C" fn decoder_new(precision: u32) -> *mut PolylineDecoder {
+    let result = update_last_error(catch_panic(|| PolylineDecoder::new(precision)));
+    result.map_or(ptr::null_mut(), |decoder| Box::into_raw(Box::new(decoder)))
+}
+
+/// Pass the next piece of a Polyline to a streaming decoder
+///
+/// Callers must pass three arguments:
+///
+/// - a pointer to a decoder created by [`decoder_new`](fn.decoder_new.html)
+/// - a pointer to the next bytes of the Polyline (`const uint8_t*`), which need not be `NUL`-terminated.
+///   This may be `NULL` if the length is `0`
+/// - the number of bytes, as a `size_t`
+///
+/// The input may be split at any byte. Every coordinate it completes is decoded immediately, and held by the decoder
+/// until it is retrieved using [`decoder_next_points`](fn.decoder_next_points.html), so callers should
+/// retrieve coordinates between calls in order to keep memory use low.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), whose `position` is an offset into the whole input fed so far.
+/// After a failure, the decoder will report the same failure from every subsequent call to this function
+/// and to [`decoder_finish`](fn.decoder_finish.html). Coordinates decoded before the failure can still be retrieved.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decoder_feed(
+    decoder: *mut PolylineDecoder,
+    data: *const u8,
+    len: libc::size_t,
+) -> PolylineResult {
+    report(catch_panic(|| {
+        let decoder = decoder_mut(decoder)?;
+        decoder.feed(input_slice(data, len)?)
+    }))
+}
+
+/// Retrieve decoded coordinates from a streaming decoder, writing them into a caller-provided buffer
+///
+/// Callers must pass four arguments:
+///
+/// - a pointer to a decoder created by [`decoder_new`](fn.decoder_new.html)
+/// - a pointer to a `double` buffer, which will receive lon, lat pairs: `[2.0, 1.0, 4.0, 3.0]`
+/// - the capacity of the buffer, in coordinate **pairs**. Its type must be `size_t`
+/// - a pointer to a `size_t`, which will receive the number of coordinate pairs written
+///
+/// Coordinates are written in input order. If fewer pairs than the capacity are written, every coordinate
+/// decoded so far has been retrieved. On failure, `out_len` will be set to `0`.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decoder_next_points(
+    decoder: *mut PolylineDecoder,
+    out_buf: *mut f64,
+    out_capacity: libc::size_t,
+    out_len: *mut libc::size_t,
+) -> PolylineResult {
+    if out_len.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let decoder = decoder_mut(decoder)?;
+        let buf = out_slice(out_buf.cast::<[f64; 2]>(), out_capacity)?;
+        Ok(decoder.next_points(buf))
+    });
+    out_len.write(*result.as_ref().unwrap_or(&0));
+    report(result.map(|_| ()))
+}
+
+/// Check that the input passed to a streaming decoder ended on a complete coordinate
+///
+/// Callers must pass a pointer to a decoder created by [`decoder_new`](fn.decoder_new.html).
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), whose status is `TruncatedInput` if the input
+/// ended part-way through a value, or with a latitude which had no longitude, or the status of an earlier failure.
+/// This doesn't free the decoder, and coordinates which haven't been retrieved can still be retrieved afterwards.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decoder_finish(decoder: *mut PolylineDecoder) -> PolylineResult {
+    report(catch_panic(|| decoder_mut(decoder)?.finish()))
+}
+
+/// Free a streaming decoder, along with any coordinates which haven't been retrieved
+///
+/// Callers must pass the same pointer they receive from [`decoder_new`](fn.decoder_new.html). Passing `NULL` has no effect.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn decoder_free(decoder: *mut PolylineDecoder) {
+    let _ = catch_panic(|| {
+        if !decoder.is_null() {
+            // we originated this data, so pointer -> box
+            drop(Box::from_raw(decoder));
+        }
+        Ok(())
+    });
+}
+
+/// An opaque handle to an incremental Polyline encoder
+///
+/// Created by [`encoder_new`](fn.encoder_new.html), and freed by [`encoder_free`](fn.encoder_free.html).
+/// An encoder holds the Polyline encoded so far, and the last coordinate pushed, so each new coordinate is encoded
+/// as a single delta.
+pub struct PolylineEncoder {
+    factor: i64,
+    encoder: PointEncoder,
+    encoded: Vec<u8>,
+}
+
+impl PolylineEncoder {
+    fn new(precision: u32) -> Result<Self, Error> {
+        let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+        Ok(PolylineEncoder {
+            factor: factor(precision),
+            encoder: PointEncoder::default(),
+            encoded: vec![],
+        })
+    }
+
+    // Append [lon, lat] pairs. Every pair is checked first, so a failure leaves the encoder unchanged
+    fn push(&mut self, coords: &[[f64; 2]], order: CoordinateOrder) -> Result<(), Error> {
+        for (idx, &coord) in coords.iter().enumerate() {
+            check_coord(order.apply(coord), idx)?;
+        }
+        let encoded = &mut self.encoded;
+        for (idx, &coord) in coords.iter().enumerate() {
+            self.encoder
+                .push(order.apply(coord), idx, self.factor, &mut |b| {
+                    encoded.push(b)
+                })?;
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> String {
+        // the Polyline alphabet is ASCII, so each byte is a char
+        self.encoded.iter().map(|&b| char::from(b)).collect()
+    }
+}
+
+// Borrow an encoder handle, which must not be NULL
+unsafe fn encoder_mut<'a>(encoder: *mut PolylineEncoder) -> Result<&'a mut PolylineEncoder, Error> {
+    encoder.as_mut().ok_or(Error::NullPointer)
+}
+
+/// Create an incremental Polyline encoder
+///
+/// Callers must pass an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+/// OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html)).
+///
+/// Returns a pointer to a new encoder, or `NULL` if the precision is invalid.
+/// Coordinates are appended using [`encoder_push`](fn.encoder_push.html) or [`encoder_push_many`](fn.encoder_push_many.html),
+/// and the Polyline encoded so far can be retrieved at any time using [`encoder_current`](fn.encoder_current.html).
+///
+/// Implementations calling this function **must** call [`encoder_free`](fn.encoder_free.html)
+/// with the returned pointer, in order to free the memory it allocates.
+#[no_mangle]
+pub extern "C" fn encoder_new(precision: u32) -> *mut PolylineEncoder {
+    let result = update_last_error(catch_panic(|| PolylineEncoder::new(precision)));
+    result.map_or(ptr::null_mut(), |encoder| Box::into_raw(Box::new(encoder)))
+}
+
+/// Append a single coordinate to an incremental encoder
+///
+/// Callers must pass three arguments:
+///
+/// - a pointer to an encoder created by [`encoder_new`](fn.encoder_new.html)
+/// - the latitude, as a `double`
+/// - the longitude, as a `double`
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html). A coordinate which is out of range is not appended,
+/// and leaves the encoder unchanged.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encoder_push(
+    encoder: *mut PolylineEncoder,
+    lat: f64,
+    lon: f64,
+) -> PolylineResult {
+    report(catch_panic(|| {
+        encoder_mut(encoder)?.push(&[[lon, lat]], CoordinateOrder::LonLat)
+    }))
+}
+
+/// Append an array of coordinates to an incremental encoder
+///
+/// Callers must pass three arguments:
+///
+/// - a pointer to an encoder created by [`encoder_new`](fn.encoder_new.html)
+/// - a [Struct](struct.ExternalArray.html) with two fields:
+///     - `data`, a void pointer to an array of floating-point coordinate pairs, in the order given by `order`
+///     - `len`, the length of the array being passed. Its type must be `size_t`
+/// - a [CoordinateOrder](enum.CoordinateOrder.html) value as an unsigned 32-bit `int`, specifying the order of each
+///   coordinate pair in `coords`
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`encode_coordinates_ffi_checked`](fn.encode_coordinates_ffi_checked.html).
+/// The status is `InvalidArgument` if `order` isn't a `CoordinateOrder` value.
+/// If any coordinate is out of range, none of them are appended, and the encoder is unchanged.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encoder_push_many(
+    encoder: *mut PolylineEncoder,
+    coords: ExternalArray,
+    order: u32,
+) -> PolylineResult {
+    report(catch_panic(|| {
+        let order = enum_arg(order, "unknown coordinate order")?;
+        let encoder = encoder_mut(encoder)?;
+        encoder.push(coords.as_slice()?, order)
+    }))
+}
+
+/// Retrieve the Polyline encoded so far by an incremental encoder
+///
+/// Callers must pass two arguments:
+///
+/// - a pointer to an encoder created by [`encoder_new`](fn.encoder_new.html)
+/// - a pointer to a `char*`, which will receive a copy of the encoded Polyline, or `NULL` on failure
+///
+/// The encoder is unchanged, and further coordinates can be appended afterwards.
+///
+/// Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+/// with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encoder_current(
+    encoder: *mut PolylineEncoder,
+    out: *mut *mut c_char,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| Ok(encoder_mut(encoder)?.current()));
+    write_string(out, result)
+}
+
+/// Free an incremental encoder
+///
+/// Callers must pass the same pointer they receive from [`encoder_new`](fn.encoder_new.html). Passing `NULL` has no effect.
+/// Polylines retrieved using [`encoder_current`](fn.encoder_current.html) are unaffected, and must still be freed.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn encoder_free(encoder: *mut PolylineEncoder) {
+    let _ = catch_panic(|| {
+        if !encoder.is_null() {
+            // we originated this data, so pointer -> box
+            drop(Box::from_raw(encoder));
+        }
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codec, PolylineStatus};
+
+    // Feed input to a new decoder in chunks of chunk_len bytes, retrieving coordinates through a small buffer
+    fn stream(input: &[u8], chunk_len: usize) -> (Vec<[f64; 2]>, PolylineResult) {
+        let decoder = decoder_new(5);
+        let mut decoded = vec![];
+        let mut buf = [0.0; 6];
+        let mut result = PolylineResult::ok();
+        for chunk in input.chunks(chunk_len) {
+            result = unsafe { decoder_feed(decoder, chunk.as_ptr(), chunk.len()) };
+            loop {
+                let mut len = 0;
+                let res = unsafe { decoder_next_points(decoder, buf.as_mut_ptr(), 3, &mut len) };
+                assert_eq!(res, PolylineResult::ok());
+                decoded.extend(buf[..len * 2].chunks(2).map(|c| [c[0], c[1]]));
+                if len < 3 {
+                    break;
+                }
+            }
+        }
+        if result == PolylineResult::ok() {
+            result = unsafe { decoder_finish(decoder) };
+        }
+        unsafe { decoder_free(decoder) };
+        (decoded, result)
+    }
+
+    #[test]
+    fn test_stream_matches_decode() {
+        let input: &str = include!("../test_fixtures/berlin_decoded.rs");
+        let expected: Vec<[f64; 2]> = codec::coords(input.as_bytes(), 5)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for chunk_len in [1, 2, 7, 64, input.len()] {
+            let (decoded, result) = stream(input.as_bytes(), chunk_len);
+            assert_eq!(result, PolylineResult::ok());
+            assert_eq!(decoded, expected);
+        }
+    }
+
+    #[test]
+    fn test_stream_failures() {
+        // the offset is into the whole input, not the chunk
+        let (decoded, result) = stream(b"_ibE_seK_ib E_seK", 4);
+        assert_eq!(decoded, [[2.0, 1.0]]);
+        assert_eq!(result.status, PolylineStatus::InvalidCharacter);
+        assert_eq!(result.position, 11);
+
+        let (_, result) = stream(b"_ibE_seK_se", 3);
+        assert_eq!(result.status, PolylineStatus::TruncatedInput);
+        assert_eq!(result.position, 8);
+        let (_, result) = stream(b"_ibE_seK_seK", 3);
+        assert_eq!(result.status, PolylineStatus::TruncatedInput);
+        assert_eq!(result.position, 8);
+
+        // a failed decoder keeps failing
+        let decoder = decoder_new(5);
+        let res = unsafe { decoder_feed(decoder, b" ".as_ptr(), 1) };
+        assert_eq!(res.status, PolylineStatus::InvalidCharacter);
+        let res = unsafe { decoder_feed(decoder, b"_ibE".as_ptr(), 4) };
+        assert_eq!(res.status, PolylineStatus::InvalidCharacter);
+        assert_eq!(res.position, 0);
+        assert_eq!(
+            unsafe { decoder_finish(decoder) }.status,
+            PolylineStatus::InvalidCharacter
+        );
+        unsafe { decoder_free(decoder) };
+    }
+
+    #[test]
+    fn test_decoder_guards() {
+        assert!(decoder_new(10).is_null());
+        let mut len = 1;
+        let res = unsafe { decoder_next_points(ptr::null_mut(), ptr::null_mut(), 0, &mut len) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        assert_eq!(len, 0);
+        let res = unsafe { decoder_feed(ptr::null_mut(), b"_ibE".as_ptr(), 4) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        // an empty feed needs no data
+        let decoder = decoder_new(5);
+        assert_eq!(
+            unsafe { decoder_feed(decoder, ptr::null(), 0) },
+            PolylineResult::ok()
+        );
+        assert_eq!(unsafe { decoder_finish(decoder) }, PolylineResult::ok());
+        unsafe {
+            decoder_free(decoder);
+            decoder_free(ptr::null_mut());
+        }
+    }
+
+    // Retrieve and free the current Polyline of an encoder
+    fn current(encoder: *mut PolylineEncoder) -> String {
+        let mut out = ptr::null_mut();
+        let res = unsafe { encoder_current(encoder, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        let encoded = unsafe { std::ffi::CStr::from_ptr(out) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { crate::drop_cstring(out) };
+        encoded
+    }
+
+    #[test]
+    fn test_incremental_encode() {
+        let input: Vec<[f64; 2]> = include!("../test_fixtures/berlin.rs");
+        let expected = codec::encode_fixture(&input, 5);
+
+        let encoder = encoder_new(5);
+        assert_eq!(current(encoder), "");
+        for &[lon, lat] in &input {
+            assert_eq!(
+                unsafe { encoder_push(encoder, lat, lon) },
+                PolylineResult::ok()
+            );
+        }
+        assert_eq!(current(encoder), expected);
+        unsafe { encoder_free(encoder) };
+
+        // push_many in two halves, swapping to lat, lon order
+        let swapped: Vec<[f64; 2]> = input.iter().map(|&[lon, lat]| [lat, lon]).collect();
+        let (first, second) = swapped.split_at(swapped.len() / 2);
+        let encoder = encoder_new(5);
+        for half in [first, second] {
+            let coords: ExternalArray = half.to_vec().into();
+            let res = unsafe { encoder_push_many(encoder, coords, CoordinateOrder::LatLon as u32) };
+            assert_eq!(res, PolylineResult::ok());
+        }
+        assert_eq!(current(encoder), expected);
+        unsafe { encoder_free(encoder) };
+    }
+
+    #[test]
+    fn test_encoder_failures() {
+        assert!(encoder_new(10).is_null());
+        let encoder = encoder_new(5);
+        assert_eq!(
+            unsafe { encoder_push(encoder, 1.0, 2.0) },
+            PolylineResult::ok()
+        );
+        let res = unsafe { encoder_push(encoder, 91.0, 2.0) };
+        assert_eq!(res.status, PolylineStatus::CoordinateOutOfRange);
+        // a failed push_many appends nothing
+        let coords: ExternalArray = vec![[4.0, 3.0], [181.0, 3.0]].into();
+        let res = unsafe { encoder_push_many(encoder, coords, CoordinateOrder::LonLat as u32) };
+        assert_eq!(res.status, PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(res.position, 1);
+        assert_eq!(current(encoder), "_ibE_seK");
+        let coords: ExternalArray = vec![[4.0, 3.0]].into();
+        let res = unsafe { encoder_push_many(encoder, coords, 2) };
+        assert_eq!(res.status, PolylineStatus::InvalidArgument);
+        assert_eq!(current(encoder), "_ibE_seK");
+
+        let mut out = ptr::null_mut();
+        let res = unsafe { encoder_current(ptr::null_mut(), &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        assert!(out.is_null());
+        let res = unsafe { encoder_push(ptr::null_mut(), 1.0, 2.0) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        unsafe {
+            encoder_free(encoder);
+            encoder_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Entry points which modify encoded Polylines directly, without converting them to floating-point coordinates
+
+use crate::codec::{factor, PointEncoder, ScaledPoint, ScaledPoints};
+use crate::error::{catch_panic, report, Error, PolylineResult};
+use crate::{get_precision, str_from_ptr, write_string, ExternalArray};
+use geo_types::Coord;
+use libc::c_char;
+use polyline::errors::PolylineError;
+
+// Find the last point of an encoded Polyline, checking every point as it would be checked when decoding
+fn last_point(bytes: &[u8], factor: i64) -> Result<Option<ScaledPoint>, Error> {
+    let mut last = None;
+    for point in ScaledPoints::checked(bytes, factor) {
+        last = Some(point?);
+    }
+    Ok(last)
+}
+
+// Append [lon, lat] coordinates to an encoded Polyline, encoding only their deltas
+fn append(incoming: &str, precision: u32, coords: &[[f64; 2]]) -> Result<String, Error> {
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    let factor = factor(precision);
+    let mut encoder = last_point(incoming.as_bytes(), factor)?
+        .map_or_else(PointEncoder::default, PointEncoder::after);
+    let mut appended = String::with_capacity(incoming.len());
+    appended.push_str(incoming);
+    for (idx, &coord) in coords.iter().enumerate() {
+        encoder.push(coord, idx, factor, &mut |b| appended.push(char::from(b)))?;
+    }
+    Ok(appended)
+}
+
+/// Append coordinates to an encoded Polyline, without decoding it
+///
+/// Callers must pass four arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`), holding the existing Polyline
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - a [Struct](struct.ExternalArray.html) with two fields:
+///     - `data`, a void pointer to an array of floating-point lon, lat coordinates: `[[2.0, 1.0]]`
+///     - `len`, the length of the array being passed. Its type must be `size_t`: `1`
+/// - a pointer to a `char*`, which will receive the extended Polyline
+///
+/// The last point of the existing Polyline is found by summing its deltas, so only the new coordinates are encoded,
+/// and the existing Polyline is copied unchanged.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html). The `position` of a failure in the existing Polyline is a byte offset,
+/// as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html), and the `position` of an out-of-range
+/// new coordinate is its index in `coords`.
+///
+/// Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+/// with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_append(
+    pl: *const c_char,
+    precision: u32,
+    coords: ExternalArray,
+    out: *mut *mut c_char,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| append(str_from_ptr(pl)?, precision, coords.as_slice()?));
+    write_string(out, result)
+}
+
+// Find the first point of an encoded Polyline, and the offset at which the rest of it begins,
+// checking every point as it would be checked when decoding
+fn split_first(bytes: &[u8], factor: i64) -> Result<(Option<ScaledPoint>, usize), Error> {
+    let mut first = None;
+    let mut rest = bytes.len();
+    for (i, point) in ScaledPoints::checked(bytes, factor).enumerate() {
+        let point = point?;
+        match i {
+            0 => first = Some(point),
+            1 => rest = point.lat_idx,
+            _ => (),
+        }
+    }
+    Ok((first, rest))
+}
+
+// Join two encoded Polylines, rewriting the first delta of b so that it's relative to the last point of a.
+// If drop_joint is set and b begins at the last point of a, that point is only included once.
+// Failures in b are reported at their offset in a followed by b
+fn concat(a: &str, b: &str, precision: u32, drop_joint: bool) -> Result<String, Error> {
+    let precision = get_precision(precision).ok_or(Error::Precision(precision))?;
+    let factor = factor(precision);
+    let last = last_point(a.as_bytes(), factor)?;
+    let (first, rest) = split_first(b.as_bytes(), factor).map_err(|e| e.offset_by(a.len()))?;
+    let mut joined = String::with_capacity(a.len() + b.len());
+    joined.push_str(a);
+    if let Some(first) = first {
+        let duplicate = last.is_some_and(|last| (last.lat, last.lon) == (first.lat, first.lon));
+        if !(drop_joint && duplicate) {
+            let mut encoder = last.map_or_else(PointEncoder::default, PointEncoder::after);
+            encoder.push_scaled(first.lat, first.lon, &mut |b| joined.push(char::from(b)));
+        }
+        // the rest of b is relative to its first point, so it's unchanged
+        joined.push_str(&b[rest..]);
+    }
+    Ok(joined)
+}
+
+/// Join two encoded Polylines, without decoding them
+///
+/// Callers must pass five arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`), holding the first Polyline
+/// - a pointer to `NUL`-terminated characters (`char*`), holding the second Polyline
+/// - an unsigned 32-bit `int` for precision (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html)),
+///   which must be the same for both Polylines
+/// - a `bool`: if `true`, and the second Polyline begins at the last point of the first,
+///   that point is only included once in the output
+/// - a pointer to a `char*`, which will receive the joined Polyline
+///
+/// Only the first delta of the second Polyline is rewritten, using the integer values of the encoding,
+/// so the joined Polyline decodes to exactly the coordinates of both inputs.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html). The `position` of a failure is a byte offset,
+/// counted as if the second Polyline followed the first.
+///
+/// Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+/// with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_concat(
+    a: *const c_char,
+    b: *const c_char,
+    precision: u32,
+    drop_joint: bool,
+    out: *mut *mut c_char,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| {
+        let a = str_from_ptr(a)?;
+        let b = str_from_ptr(b).map_err(|e| e.offset_by(a.len()))?;
+        concat(a, b, precision, drop_joint)
+    });
+    write_string(out, result)
+}
+
+// Rescale a value from one precision to another, rounding half away from zero as encoding does.
+// Returns None if upscaling overflows
+fn rescale(value: i64, from: u32, to: u32) -> Option<i64> {
+    if to >= from {
+        return value.checked_mul(10i64.pow(to - from));
+    }
+    let divisor = 10i64.pow(from - to);
+    let (quotient, remainder) = (value / divisor, value % divisor);
+    if remainder.abs() >= divisor - remainder.abs() {
+        Some(quotient + value.signum())
+    } else {
+        Some(quotient)
+    }
+}
+
+// Re-encode a Polyline at a different precision, rescaling the absolute integer values of each point.
+// Rescaling the deltas directly would accumulate rounding errors
+fn transcode(incoming: &str, from: u32, to: u32) -> Result<String, Error> {
+    let from = get_precision(from).ok_or(Error::Precision(from))?;
+    let to = get_precision(to).ok_or(Error::Precision(to))?;
+    let factor = factor(from);
+    let mut encoder = PointEncoder::default();
+    let mut transcoded = String::with_capacity(incoming.len());
+    for point in ScaledPoints::checked(incoming.as_bytes(), factor) {
+        let point = point?;
+        let [lon, lat] = point.to_coord(factor)?;
+        let overflow = || PolylineError::CoordEncodingError {
+            coord: Coord { x: lon, y: lat },
+            idx: point.lat_idx,
+        };
+        let lat = rescale(point.lat, from, to).ok_or_else(overflow)?;
+        let lon = rescale(point.lon, from, to).ok_or_else(overflow)?;
+        encoder.push_scaled(lat, lon, &mut |b| transcoded.push(char::from(b)));
+    }
+    Ok(transcoded)
+}
+
+/// Convert a Polyline from one precision to another, without decoding it into floating-point coordinates
+///
+/// Callers must pass four arguments:
+///
+/// - a pointer to `NUL`-terminated characters (`char*`)
+/// - an unsigned 32-bit `int` for the precision of the input (5 for Google Polylines, 6 for
+///   OSRM and Valhalla Polylines, up to [`polyline_max_precision`](fn.polyline_max_precision.html))
+/// - an unsigned 32-bit `int` for the precision of the output
+/// - a pointer to a `char*`, which will receive the converted Polyline
+///
+/// The integer value of each coordinate is rescaled directly. When reducing precision, values are rounded
+/// half away from zero, which is how coordinates are rounded when encoding.
+///
+/// Returns a [PolylineResult](struct.PolylineResult.html), as for [`decode_polyline_ffi_checked`](fn.decode_polyline_ffi_checked.html).
+/// The status is `EncodingError` if increasing the precision of a value would overflow, with the `position` of its point.
+///
+/// Implementations calling this function **must** call [`drop_cstring`](fn.drop_cstring.html)
+/// with a non-`NULL` pointer written to `out`, in order to free the memory it allocates.
+///
+/// # Safety
+///
+/// This function is unsafe because it accesses raw pointers which could contain arbitrary data
+#[no_mangle]
+pub unsafe extern "C" fn polyline_transcode(
+    pl: *const c_char,
+    from_precision: u32,
+    to_precision: u32,
+    out: *mut *mut c_char,
+) -> PolylineResult {
+    if out.is_null() {
+        return report(Err(Error::NullPointer));
+    }
+    let result = catch_panic(|| transcode(str_from_ptr(pl)?, from_precision, to_precision));
+    write_string(out, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codec, drop_cstring, PolylineStatus};
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    #[test]
+    fn test_append() {
+        let input: Vec<[f64; 2]> = include!("../test_fixtures/berlin.rs");
+        for precision in [5, 6] {
+            let (first, second) = input.split_at(input.len() / 3);
+            let pl = CString::new(codec::encode_fixture(first, precision)).unwrap();
+            let coords: ExternalArray = second.to_vec().into();
+            let mut out = ptr::null_mut();
+            let res = unsafe { polyline_append(pl.as_ptr(), precision, coords, &mut out) };
+            assert_eq!(res, PolylineResult::ok());
+            let appended = unsafe { CStr::from_ptr(out) }.to_str().unwrap();
+            assert_eq!(appended, codec::encode_fixture(&input, precision));
+            unsafe { drop_cstring(out) };
+        }
+        // appending to an empty Polyline is the same as encoding
+        assert_eq!(append("", 5, &[[2.0, 1.0]]).unwrap(), "_ibE_seK");
+        assert_eq!(append("_ibE_seK", 5, &[]).unwrap(), "_ibE_seK");
+    }
+
+    #[test]
+    fn test_append_failures() {
+        let err = append("_ibE_seK_se", 5, &[[2.0, 1.0]]).unwrap_err();
+        assert_eq!(err.status(), PolylineStatus::TruncatedInput);
+        assert_eq!(err.position(), 8);
+        let err = append("_ibE_seK", 5, &[[2.0, 1.0], [2.0, 91.0]]).unwrap_err();
+        assert_eq!(err.status(), PolylineStatus::CoordinateOutOfRange);
+        assert_eq!(err.position(), 1);
+        assert_eq!(append("", 10, &[]).unwrap_err(), Error::Precision(10));
+
+        let mut out = ptr::null_mut();
+        let coords: ExternalArray = vec![[2.0, 1.0]].into();
+        let res = unsafe { polyline_append(ptr::null(), 5, coords, &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+        assert!(out.is_null());
+    }
+
+    #[test]
+    fn test_concat() {
+        let input: Vec<[f64; 2]> = include!("../test_fixtures/berlin.rs");
+        let (first, second) = input.split_at(input.len() / 2);
+        let (a, b) = (
+            codec::encode_fixture(first, 6),
+            codec::encode_fixture(second, 6),
+        );
+        let expected = codec::encode_fixture(&input, 6);
+        for drop_joint in [false, true] {
+            assert_eq!(concat(&a, &b, 6, drop_joint).unwrap(), expected);
+        }
+        // the joint point is only dropped when asked
+        let b = codec::encode_fixture(&input[first.len() - 1..], 6);
+        assert_ne!(concat(&a, &b, 6, false).unwrap(), expected);
+        assert_eq!(concat(&a, &b, 6, true).unwrap(), expected);
+        // either side may be empty
+        assert_eq!(concat("", &a, 6, true).unwrap(), a);
+        assert_eq!(concat(&a, "", 6, true).unwrap(), a);
+    }
+
+    #[test]
+    fn test_concat_failures() {
+        let a = CString::new("_ibE_seK").unwrap();
+        let b = CString::new("_ibE_seK_ib E").unwrap();
+        let mut out = ptr::null_mut();
+        let res = unsafe { polyline_concat(a.as_ptr(), b.as_ptr(), 5, false, &mut out) };
+        assert_eq!(res.status, PolylineStatus::InvalidCharacter);
+        assert_eq!(res.position, 8 + 11);
+        assert!(out.is_null());
+        let res = unsafe { polyline_concat(b.as_ptr(), a.as_ptr(), 5, false, &mut out) };
+        assert_eq!(res.position, 11);
+        let res = unsafe { polyline_concat(a.as_ptr(), ptr::null(), 5, false, &mut out) };
+        assert_eq!(res.status, PolylineStatus::NullPointer);
+    }
+
+    #[test]
+    fn test_transcode() {
+        let input: Vec<[f64; 2]> = include!("../test_fixtures/berlin.rs");
+        let p5 = codec::encode_fixture(&input, 5);
+        let p6 = codec::encode_fixture(&input, 6);
+        // increasing precision is exact, and reversible
+        let upscaled = transcode(&p5, 5, 7).unwrap();
+        assert_eq!(transcode(&upscaled, 7, 5).unwrap(), p5);
+        assert_eq!(transcode(&p6, 6, 6).unwrap(), p6);
+
+        let pl = CString::new(p6).unwrap();
+        let mut out = ptr::null_mut();
+        let res = unsafe { polyline_transcode(pl.as_ptr(), 6, 5, &mut out) };
+        assert_eq!(res, PolylineResult::ok());
+        let transcoded = unsafe { CStr::from_ptr(out) }.to_str().unwrap();
+        let decoded: Vec<[f64; 2]> = codec::coords(transcoded.as_bytes(), 5)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for (coord, original) in decoded.iter().zip(&input) {
+            assert!((coord[0] - original[0]).abs() <= 0.000_01);
+            assert!((coord[1] - original[1]).abs() <= 0.000_01);
+        }
+        unsafe { drop_cstring(out) };
+    }
+
+    #[test]
+    fn test_rescale() {
+        // half away from zero
+        assert_eq!(rescale(15, 1, 0), Some(2));
+        assert_eq!(rescale(-15, 1, 0), Some(-2));
+        assert_eq!(rescale(14, 1, 0), Some(1));
+        assert_eq!(rescale(-16, 1, 0), Some(-2));
+        assert_eq!(rescale(1_234_550, 6, 5), Some(123_455));
+        assert_eq!(rescale(i64::MAX / 2, 0, 1), None);
+
+        let err = transcode("_ibE_seK_se", 5, 6).unwrap_err();
+        assert_eq!(err.status(), PolylineStatus::TruncatedInput);
+        assert_eq!(transcode("", 5, 10), Err(Error::Precision(10)));
+    }
+}
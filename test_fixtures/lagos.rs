@@ -0,0 +1,52 @@
+vec![
+    [6.455000, 3.384100],
+    [6.455270, 3.384102],
+    [6.455529, 3.384178],
+    [6.455766, 3.384308],
+    [6.456024, 3.384388],
+    [6.456282, 3.384468],
+    [6.456543, 3.384536],
+    [6.456793, 3.384637],
+    [6.457015, 3.384792],
+    [6.457274, 3.384868],
+    [6.457542, 3.384843],
+    [6.457808, 3.384890],
+    [6.458076, 3.384923],
+    [6.458331, 3.385011],
+    [6.458600, 3.384993],
+    [6.458868, 3.384963],
+    [6.459137, 3.384981],
+    [6.459404, 3.384940],
+    [6.459668, 3.384995],
+    [6.459902, 3.385132],
+    [6.460168, 3.385173],
+    [6.460431, 3.385111],
+    [6.460696, 3.385059],
+    [6.460962, 3.385101],
+    [6.461232, 3.385117],
+    [6.461498, 3.385072],
+    [6.461760, 3.385011],
+    [6.461982, 3.384857],
+    [6.462165, 3.384657],
+    [6.462337, 3.384448],
+    [6.462509, 3.384239],
+    [6.462633, 3.383997],
+    [6.462703, 3.383735],
+    [6.462713, 3.383464],
+    [6.462714, 3.383192],
+    [6.462670, 3.382924],
+    [6.462529, 3.382692],
+    [6.462456, 3.382431],
+    [6.462394, 3.382167],
+    [6.462362, 3.381897],
+    [6.462265, 3.381644],
+    [6.462271, 3.381373],
+    [6.462354, 3.381114],
+    [6.462357, 3.380843],
+    [6.462324, 3.380573],
+    [6.462338, 3.380302],
+    [6.462397, 3.380037],
+    [6.462543, 3.379809],
+    [6.462675, 3.379572],
+    [6.462863, 3.379377],
+]
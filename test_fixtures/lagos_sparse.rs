@@ -0,0 +1,8 @@
+vec![
+    [6.45500, 3.38410],
+    [6.45610, 3.38460],
+    [6.45720, 3.38520],
+    [6.45800, 3.38610],
+    [6.45890, 3.38690],
+    [6.45980, 3.38760],
+]